@@ -1,32 +1,1342 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, collections::VecDeque};
+use core::{
+    fmt,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    ptr,
+    slice,
+};
+
+/// The reason of [`iunpack!`] failure, use `else(err)` to get it
+///
+/// It is non-exhaustive, because the variants depend on the features.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnpackError {
+    /// The iterator is stopped before all patterns get an element
+    TooFew {
+        /// Count of elements taken from the iterator
+        got: usize,
+        /// Count of required patterns
+        expected: usize,
+    },
+    /// The iterator has more elements after all patterns
+    TooMany {
+        /// Count of patterns
+        expected: usize,
+    },
+    /// The element does not match its pattern
+    Mismatch {
+        /// Index of the pattern
+        index: usize,
+        /// The index is counted from the back
+        from_back: bool,
+    },
+    /// The element does not satisfy the guard of its pattern
+    Guard {
+        /// Index of the pattern
+        index: usize,
+        /// The index is counted from the back
+        from_back: bool,
+    },
+    /// All elements are matched, but the guard of whole patterns is not satisfied
+    MatchGuard,
+    /// The count of elements taken by the star pattern is out of its bounds
+    StarBounds {
+        /// Count of elements taken by the star pattern,
+        /// it stops at one more than `max`
+        got: usize,
+        /// Minimum count of the bounds
+        min: usize,
+        /// Maximum count of the bounds, [`None`] is unbounded
+        max: Option<usize>,
+    },
+    /// The bounds of the star pattern are empty, like `*name{..0}`
+    EmptyBounds,
+    /// The separator between two star patterns is not found
+    NoSeparator,
+    /// The star pattern elements exceed the capacity of the collection, like [`FixedVec`]
+    Overflow {
+        /// Capacity of the collection
+        capacity: usize,
+    },
+    /// The elements do not match the sequence patterns of [`imatch_seq!`]
+    ///
+    /// [`imatch_seq!`]: macro.imatch_seq.html
+    NoMatch,
+    /// The element does not match its nested pattern
+    #[cfg(feature = "alloc")]
+    Nested {
+        /// Index of the nested pattern
+        index: usize,
+        /// The index is counted from the back
+        from_back: bool,
+        /// The failure of nested pattern
+        error: Box<UnpackError>,
+    },
+}
+
+impl fmt::Display for UnpackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::TooFew { got, expected } => write!(
+                f,
+                "not enough elements, expected {expected}, got {got}",
+            ),
+            Self::TooMany { expected } => write!(
+                f,
+                "too many elements, expected {expected}",
+            ),
+            Self::Mismatch { index, from_back: false } => write!(
+                f,
+                "element {index} does not match the pattern",
+            ),
+            Self::Mismatch { index, from_back: true } => write!(
+                f,
+                "element {index} from the back does not match the pattern",
+            ),
+            Self::Guard { index, from_back: false } => write!(
+                f,
+                "element {index} does not satisfy the guard",
+            ),
+            Self::Guard { index, from_back: true } => write!(
+                f,
+                "element {index} from the back does not satisfy the guard",
+            ),
+            Self::MatchGuard => write!(f, "the guard is not satisfied"),
+            Self::NoSeparator => write!(f, "the separator is not found"),
+            Self::Overflow { capacity } => write!(
+                f,
+                "star pattern elements exceed the capacity {capacity}",
+            ),
+            Self::NoMatch => write!(f, "the elements do not match the sequence"),
+            Self::StarBounds { got, min, max: Some(max) } => write!(
+                f,
+                "star pattern expected {min}..={max} elements, got {got}",
+            ),
+            Self::StarBounds { got, min, max: None } => write!(
+                f,
+                "star pattern expected at least {min} elements, got {got}",
+            ),
+            Self::EmptyBounds => write!(f, "star pattern bounds are empty"),
+            #[cfg(feature = "alloc")]
+            Self::Nested { index, from_back: false, ref error } => write!(
+                f,
+                "element {index}: {error}",
+            ),
+            #[cfg(feature = "alloc")]
+            Self::Nested { index, from_back: true, ref error } => write!(
+                f,
+                "element {index} from the back: {error}",
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnpackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "alloc")]
+            Self::Nested { error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    use core::fmt::Debug;
+    use core::marker::PhantomData;
+    use core::ops::{Bound, RangeBounds};
+    use crate::{TryExtend, UnpackError};
+
+    #[cfg(feature = "alloc")]
+    pub use alloc::{boxed::Box, vec::Vec};
+    #[cfg(feature = "alloc")]
+    use {
+        alloc::{format, string::String, vec},
+        core::cell::Cell,
+        core::iter::{Chain, Rev},
+        core::slice,
+    };
+
+    /// The bounds of star pattern, like `*name{1..16}`
+    #[derive(Debug, Clone, Copy)]
+    pub struct StarBounds {
+        min: usize,
+        max: Option<usize>,
+        empty: bool,
+    }
+
+    impl StarBounds {
+        pub fn new<R: RangeBounds<usize>>(range: R) -> Self {
+            let min = match range.start_bound() {
+                Bound::Included(&n) => n,
+                Bound::Excluded(&n) => n.saturating_add(1),
+                Bound::Unbounded => 0,
+            };
+            let (max, empty) = match range.end_bound() {
+                Bound::Included(&n) => (Some(n), n < min),
+                Bound::Excluded(&n) => match n.checked_sub(1) {
+                    Some(max) => (Some(max), max < min),
+                    None => (None, true),
+                },
+                Bound::Unbounded => (None, false),
+            };
+            Self { min, max, empty }
+        }
+
+        /// Count of elements to take, one more than `max` to detect overflow,
+        /// nothing is taken for empty bounds
+        pub fn limit(&self) -> usize {
+            match self.max {
+                _ if self.empty => 0,
+                Some(max) => max.saturating_add(1),
+                None => usize::MAX,
+            }
+        }
+
+        pub fn check(&self, got: usize) -> Result<(), UnpackError> {
+            match self.max {
+                _ if self.empty => return Err(UnpackError::EmptyBounds),
+                Some(max) if got > max => {},
+                _ if got < self.min => {},
+                _ => return Ok(()),
+            }
+            Err(UnpackError::StarBounds { got, min: self.min, max: self.max })
+        }
+    }
+
+    /// The collection of star pattern,
+    /// use `(&Collector::<C, _>::new()).collect(iter)` to prefer [`TryExtend`],
+    /// and fallback to [`FromIterator`], or [`Extend`] for `*&mut buf`
+    pub struct Collector<C, T>(PhantomData<fn(T) -> C>);
+
+    impl<C, T> Collector<C, T> {
+        pub const fn new() -> Self {
+            Self(PhantomData)
+        }
+
+        /// The collector of existing collection, like `*&mut buf`
+        pub const fn of(_coll: &C) -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    impl<C, T> Default for Collector<C, T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    pub trait TryCollect {
+        type Item;
+        type Output;
+
+        fn extend<I>(&self, coll: &mut Self::Output, iter: I) -> Result<(), UnpackError>
+        where I: IntoIterator<Item = Self::Item>;
+
+        fn collect<I>(&self, iter: I) -> Result<Self::Output, UnpackError>
+        where I: IntoIterator<Item = Self::Item>,
+              Self::Output: Default;
+    }
+
+    impl<C: TryExtend<T>, T> TryCollect for Collector<C, T> {
+        type Item = T;
+        type Output = C;
+
+        fn extend<I>(&self, coll: &mut C, iter: I) -> Result<(), UnpackError>
+        where I: IntoIterator<Item = T>,
+        {
+            coll.try_extend(iter)
+        }
+
+        fn collect<I>(&self, iter: I) -> Result<C, UnpackError>
+        where I: IntoIterator<Item = T>,
+              C: Default,
+        {
+            let mut coll = C::default();
+            coll.try_extend(iter)?;
+            Ok(coll)
+        }
+    }
+
+    pub trait Collect {
+        type Item;
+        type Output;
+
+        fn collect<I>(&self, iter: I) -> Result<Self::Output, UnpackError>
+        where I: IntoIterator<Item = Self::Item>;
+    }
+
+    impl<C: FromIterator<T>, T> Collect for &Collector<C, T> {
+        type Item = T;
+        type Output = C;
+
+        fn collect<I>(&self, iter: I) -> Result<C, UnpackError>
+        where I: IntoIterator<Item = T>,
+        {
+            Ok(C::from_iter(iter))
+        }
+    }
+
+    pub trait ExtendCollect {
+        type Item;
+        type Output;
+
+        fn extend<I>(&self, coll: &mut Self::Output, iter: I) -> Result<(), UnpackError>
+        where I: IntoIterator<Item = Self::Item>;
+    }
+
+    impl<C: Extend<T>, T> ExtendCollect for &Collector<C, T> {
+        type Item = T;
+        type Output = C;
+
+        fn extend<I>(&self, coll: &mut C, iter: I) -> Result<(), UnpackError>
+        where I: IntoIterator<Item = T>,
+        {
+            coll.extend(iter);
+            Ok(())
+        }
+    }
+
+    /// The `next_back` of the iterator
+    pub type NextBack<I> = fn(&mut I) -> Option<<I as Iterator>::Item>;
+
+    /// Select [`DoubleEndedIterator::next_back`] when the iterator implements it, by autoref
+    pub struct Probe<I>(PhantomData<I>);
+
+    impl<I> Probe<I> {
+        pub fn of(_: &I) -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    pub trait ViaDoubleEnded<I: Iterator> {
+        fn next_back_fn(&self) -> Option<NextBack<I>>;
+    }
+
+    impl<I: DoubleEndedIterator> ViaDoubleEnded<I> for Probe<I> {
+        fn next_back_fn(&self) -> Option<NextBack<I>> {
+            Some(I::next_back)
+        }
+    }
+
+    pub trait ViaForward<I: Iterator> {
+        fn next_back_fn(&self) -> Option<NextBack<I>> {
+            None
+        }
+    }
+
+    impl<I: Iterator> ViaForward<I> for &Probe<I> {}
+
+    /// The end elements of `*` with end patterns, taken by the `next_back` selected by [`Probe`],
+    /// or delayed by the buffer like `**` without `next_back`
+    pub struct Ends<'a, I: Iterator> {
+        iter: &'a mut I,
+        next_back: Option<NextBack<I>>,
+        buf: &'a mut [Option<I::Item>],
+        start: usize,
+        index: usize,
+        count: usize,
+        limit: usize,
+        full: bool,
+    }
+
+    impl<'a, I: Iterator> Ends<'a, I> {
+        /// The buffer has `start` optional elements and then the end elements,
+        /// it is filled in order without `next_back`,
+        /// and at most `limit` middle elements are taken
+        pub fn new(
+            iter: &'a mut I,
+            next_back: Option<NextBack<I>>,
+            buf: &'a mut [Option<I::Item>],
+            start: usize,
+            limit: usize,
+        ) -> Self {
+            let mut ends = Ends { iter, next_back, buf, start, index: 0, count: 0, limit, full: false };
+            if ends.next_back.is_none() {
+                ends.fill();
+            }
+            ends
+        }
+
+        fn fill(&mut self) {
+            let mut got = 0;
+            for slot in self.buf.iter_mut() {
+                *slot = self.iter.next();
+                if slot.is_none() {
+                    break;
+                }
+                got += 1;
+            }
+            let len = self.buf.len();
+            self.full = got == len;
+            if !self.full {
+                // the end elements are filled before the optional elements
+                self.buf[got.saturating_sub(len - self.start)..].rotate_right(len - got);
+                self.limit = 0;
+            }
+        }
+
+        /// The middle elements are delayed by the filled buffer,
+        /// so they are taken before the end elements
+        pub fn is_delayed(&self) -> bool {
+            self.full
+        }
+
+        /// The middle elements
+        pub fn middle(&mut self) -> Middle<'_, 'a, I> {
+            Middle(self)
+        }
+    }
+
+    impl<I: Iterator> Iterator for Ends<'_, I> {
+        type Item = I::Item;
+
+        /// The optional elements
+        fn next(&mut self) -> Option<Self::Item> {
+            match self.next_back {
+                Some(_) => self.iter.next(),
+                None => self.buf[..self.start].iter_mut().find_map(Option::take),
+            }
+        }
+    }
+
+    impl<I: Iterator> DoubleEndedIterator for Ends<'_, I> {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            if let Some(next_back) = self.next_back {
+                return next_back(self.iter);
+            }
+            let ends = &mut self.buf[self.start..];
+            ends.rotate_left(self.index);
+            self.index = 0;
+            ends.iter_mut().rev().find_map(Option::take)
+        }
+    }
+
+    /// The middle elements of [`Ends`]
+    pub struct Middle<'b, 'a, I: Iterator>(&'b mut Ends<'a, I>);
+
+    impl<I: Iterator> Middle<'_, '_, I> {
+        /// The count of elements taken
+        pub fn taken(&self) -> usize {
+            self.0.count
+        }
+    }
+
+    impl<I: Iterator> Iterator for Middle<'_, '_, I> {
+        type Item = I::Item;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let ends = &mut *self.0;
+            if ends.count == ends.limit {
+                return None;
+            }
+            let elem = ends.iter.next()?;
+            ends.count += 1;
+            if ends.next_back.is_some() {
+                return Some(elem);
+            }
+            let ring = &mut ends.buf[ends.start..];
+            let elem = ring[ends.index].replace(elem);
+            ends.index = (ends.index + 1) % ring.len();
+            elem
+        }
+    }
+
+    /// Take elements while the predicate is satisfied,
+    /// and keep the first unsatisfied element, like `*name while pred`
+    pub struct TakeWhile<'a, I: Iterator, P> {
+        iter: &'a mut I,
+        rest: &'a mut Option<I::Item>,
+        pred: P,
+    }
+
+    impl<'a, I, P> TakeWhile<'a, I, P>
+    where I: Iterator,
+          P: FnMut(&I::Item) -> bool,
+    {
+        pub fn new(iter: &'a mut I, rest: &'a mut Option<I::Item>, pred: P) -> Self {
+            Self { iter, rest, pred }
+        }
+    }
+
+    impl<I, P> Iterator for TakeWhile<'_, I, P>
+    where I: Iterator,
+          P: FnMut(&I::Item) -> bool,
+    {
+        type Item = I::Item;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.rest.is_some() {
+                return None;
+            }
+            let elem = self.iter.next()?;
+            if (self.pred)(&elem) {
+                Some(elem)
+            } else {
+                *self.rest = Some(elem);
+                None
+            }
+        }
+    }
+
+    /// The quantified element pattern of `imatch_seq!`
+    pub struct Quant<'a, T> {
+        min: usize,
+        max: usize,
+        pred: &'a dyn Fn(&T) -> bool,
+    }
+
+    impl<'a, T> Quant<'a, T> {
+        /// The `buf` is only used to infer the element type of `pred`
+        pub fn new<F>(_buf: &[T], min: usize, max: usize, pred: &'a F) -> Self
+        where F: Fn(&T) -> bool,
+        {
+            Self { min, max, pred }
+        }
+    }
+
+    /// Match the buffered elements with backtracking,
+    /// result the count of elements taken by each pattern
+    pub fn match_seq<T, const N: usize>(
+        buf: &[T],
+        quants: &[Quant<'_, T>; N],
+    ) -> Option<[usize; N]> {
+        let mut lens = [0; N];
+        match_quants(buf, quants, &mut lens).then_some(lens)
+    }
+
+    fn match_quants<T>(buf: &[T], quants: &[Quant<'_, T>], lens: &mut [usize]) -> bool {
+        let Some((quant, quants)) = quants.split_first() else {
+            return buf.is_empty();
+        };
+        // greedy, try fewer elements when the rest patterns fail
+        let mut len = buf.iter()
+            .take(quant.max)
+            .take_while(|elem| (quant.pred)(elem))
+            .count();
+        while len >= quant.min {
+            if match_quants(&buf[len..], quants, &mut lens[1..]) {
+                lens[0] = len;
+                return true;
+            }
+            if len == 0 {
+                break;
+            }
+            len -= 1;
+        }
+        false
+    }
+
+    /// The buffered elements of [`imatch!`](crate::imatch), shared by all arms
+    #[cfg(feature = "alloc")]
+    pub struct Lookahead<T> {
+        buf: Vec<T>,
+        done: bool,
+        taken: Cell<usize>,
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<T> Default for Lookahead<T> {
+        fn default() -> Self {
+            Self { buf: Vec::new(), done: false, taken: Cell::new(0) }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<T> Lookahead<T> {
+        /// Buffer at least `n` elements from `iter` if any
+        pub fn fill<I>(&mut self, iter: &mut I, n: usize)
+        where I: Iterator<Item = T>,
+        {
+            if let (false, Some(more)) = (self.done, n.checked_sub(self.buf.len())) {
+                let len = self.buf.len();
+                self.buf.extend(iter.take(more));
+                self.done = self.buf.len() - len < more;
+            }
+        }
+
+        pub fn iter(&self) -> slice::Iter<'_, T> {
+            self.buf.iter()
+        }
+
+        /// Like `iter`, but count the elements taken for `imatch!(try ...)`,
+        /// it is not double-ended, so the end patterns delay the elements like `iunpack!(try ...)`
+        pub fn track(&self) -> Track<'_, T> {
+            self.taken.set(0);
+            Track { iter: self.buf.iter(), taken: &self.taken }
+        }
+
+        /// The count of elements taken from the last `track`
+        pub fn taken(&self) -> usize {
+            self.taken.get()
+        }
+    }
+
+    /// The buffered elements counted by [`Lookahead::track`]
+    #[cfg(feature = "alloc")]
+    pub struct Track<'a, T> {
+        iter: slice::Iter<'a, T>,
+        taken: &'a Cell<usize>,
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<'a, T> Iterator for Track<'a, T> {
+        type Item = &'a T;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let elem = self.iter.next()?;
+            self.taken.set(self.taken.get() + 1);
+            Some(elem)
+        }
+    }
+
+    /// The remaining iterator of `else(err, consumed, rest)`
+    #[cfg(feature = "alloc")]
+    pub type Rest<I> = Chain<I, Rev<vec::IntoIter<<I as Iterator>::Item>>>;
+
+    /// Record the clones of taken elements, for `else(err, consumed, rest)`
+    #[cfg(feature = "alloc")]
+    pub struct Recover<I: Iterator> {
+        iter: I,
+        front: Vec<I::Item>,
+        back: Vec<I::Item>,
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<I: Iterator> Recover<I> {
+        pub fn new<T>(iter: T) -> Self
+        where T: IntoIterator<IntoIter = I>,
+        {
+            Self { iter: iter.into_iter(), front: Vec::new(), back: Vec::new() }
+        }
+
+        /// Result the elements taken from the front in order,
+        /// and the remaining iterator followed by the elements taken from the back
+        pub fn into_parts(self) -> (Vec<I::Item>, Rest<I>) {
+            (self.front, self.iter.chain(self.back.into_iter().rev()))
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<I: Iterator> Iterator for Recover<I>
+    where I::Item: Clone,
+    {
+        type Item = I::Item;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let elem = self.iter.next()?;
+            self.front.push(elem.clone());
+            Some(elem)
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.iter.size_hint()
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<I: DoubleEndedIterator> DoubleEndedIterator for Recover<I>
+    where I::Item: Clone,
+    {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            let elem = self.iter.next_back()?;
+            self.back.push(elem.clone());
+            Some(elem)
+        }
+    }
+
+    /// The [`Debug`] of elements taken by [`iassert_unpack!`](crate::iassert_unpack)
+    #[cfg(feature = "alloc")]
+    #[derive(Default)]
+    pub struct Seen {
+        front: Vec<String>,
+        back: Vec<String>,
+    }
+
+    #[cfg(feature = "alloc")]
+    impl Seen {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn record<I>(&mut self, iter: I) -> Record<'_, I::IntoIter>
+        where I: IntoIterator,
+              I::Item: Debug,
+        {
+            Record { iter: iter.into_iter(), seen: self }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    pub struct Record<'a, I> {
+        iter: I,
+        seen: &'a mut Seen,
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<I: Iterator> Iterator for Record<'_, I>
+    where I::Item: Debug,
+    {
+        type Item = I::Item;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let elem = self.iter.next()?;
+            self.seen.front.push(format!("{elem:?}"));
+            Some(elem)
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.iter.size_hint()
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<I: DoubleEndedIterator> DoubleEndedIterator for Record<'_, I>
+    where I::Item: Debug,
+    {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            let elem = self.iter.next_back()?;
+            self.seen.back.push(format!("{elem:?}"));
+            Some(elem)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[cold]
+    #[track_caller]
+    pub fn assert_failed(pattern: &str, err: &UnpackError, seen: &Seen) -> ! {
+        let consumed: Vec<&str> = seen.front.iter()
+            .chain(seen.back.iter().rev())
+            .map(String::as_str)
+            .collect();
+        let elem = match *err {
+            | UnpackError::Mismatch { index, from_back: true }
+            | UnpackError::Guard { index, from_back: true }
+            | UnpackError::Nested { index, from_back: true, .. } => {
+                consumed.len().checked_sub(index + 1).map(|i| consumed[i])
+            },
+            | UnpackError::Mismatch { from_back: false, .. }
+            | UnpackError::Guard { from_back: false, .. }
+            | UnpackError::Nested { from_back: false, .. }
+            | UnpackError::TooMany { .. } => {
+                seen.front.last().map(String::as_str)
+            },
+            _ => None,
+        };
+        let consumed = consumed.join(", ");
+        match elem {
+            Some(elem) => panic!(
+                "assertion `iunpack!({pattern})` failed: {err}\n \
+                 element: {elem}\n\
+                 consumed: [{consumed}]",
+            ),
+            None => panic!(
+                "assertion `iunpack!({pattern})` failed: {err}\n\
+                 consumed: [{consumed}]",
+            ),
+        }
+    }
+}
+
+/// Tuple types which can be collected by [`IterUnpackExt::collect_tuple`]
+///
+/// It is implemented for homogeneous tuples of up to 12 elements.
+pub trait TupleCollect: Sized {
+    /// The element type of the tuple
+    type Item;
+
+    /// Collect exactly the count of tuple elements from iterator
+    fn collect_from<I>(iter: I) -> Result<Self, UnpackError>
+    where I: Iterator<Item = Self::Item>;
+}
+
+macro_rules! impl_tuple_collect {
+    (@impl $($name:ident)+) => {
+        impl<T> TupleCollect for ($(iunpack!(@if(T) else $name),)+) {
+            type Item = T;
+
+            fn collect_from<I>(iter: I) -> Result<Self, UnpackError>
+            where I: Iterator<Item = Self::Item>,
+            {
+                iunpack!($($name),+ = iter)
+            }
+        }
+    };
+    ($first:ident $($name:ident)*) => {
+        impl_tuple_collect!(@impl $first $($name)*);
+        impl_tuple_collect!($($name)*);
+    };
+    () => {};
+}
+impl_tuple_collect!(a b c d e f g h i j k l);
+
+/// Extension methods of [`Iterator`] for unpacking without macros
+///
+/// # Examples
+/// ```
+/// # use itermacros::{IterUnpackExt, UnpackError};
+/// let mut iter = 0..5;
+/// assert_eq!(iter.next_array(), Some([0, 1]));
+/// assert_eq!(iter.next_array(), Some([2, 3]));
+/// assert_eq!(iter.next_array::<2>(), None);
+///
+/// assert_eq!((0..3).collect_array(), Ok([0, 1, 2]));
+/// assert_eq!((0..2).collect_array::<3>(), Err(UnpackError::TooFew { got: 2, expected: 3 }));
+/// assert_eq!((0..4).collect_array::<3>(), Err(UnpackError::TooMany { expected: 3 }));
+///
+/// assert_eq!("a b".split(' ').collect_tuple(), Ok(("a", "b")));
+/// assert_eq!("a".split(' ').collect_tuple::<(_, _)>(),
+///            Err(UnpackError::TooFew { got: 1, expected: 2 }));
+/// ```
+pub trait IterUnpackExt: Iterator {
+    /// Take the next `N` elements, return [`None`] if the iterator is stopped before
+    fn next_array<const N: usize>(&mut self) -> Option<[Self::Item; N]> {
+        fill_array(self).ok()
+    }
+
+    /// Collect all elements into array,
+    /// fail if the count of elements is not `N`, like [`iunpack!`]
+    fn collect_array<const N: usize>(mut self) -> Result<[Self::Item; N], UnpackError>
+    where Self: Sized,
+    {
+        let arr = fill_array(&mut self)
+            .map_err(|got| UnpackError::TooFew { got, expected: N })?;
+        match self.next() {
+            Some(_) => Err(UnpackError::TooMany { expected: N }),
+            None => Ok(arr),
+        }
+    }
+
+    /// Collect all elements into tuple,
+    /// fail if the count of elements is not the length of tuple, like [`iunpack!`]
+    fn collect_tuple<T>(self) -> Result<T, UnpackError>
+    where Self: Sized,
+          T: TupleCollect<Item = Self::Item>,
+    {
+        T::collect_from(self)
+    }
+
+    /// Create a [`MultiPeek`] which can peek any number of elements
+    #[cfg(feature = "alloc")]
+    fn multipeek(self) -> MultiPeek<Self>
+    where Self: Sized,
+    {
+        MultiPeek::new(self)
+    }
+}
+
+impl<I: Iterator + ?Sized> IterUnpackExt for I {}
+
+/// Iterators which can take elements tentatively, used by `iunpack!(try ...)`
+///
+/// It is implemented for the lookahead adapter [`MultiPeek`] of cloneable elements,
+/// the elements are peeked into its buffer, the source iterator is not cloned.
+/// Use [`IterUnpackExt::multipeek`] for other iterators.
+///
+/// [`MultiPeek`]: struct.MultiPeek.html
+/// [`IterUnpackExt::multipeek`]: trait.IterUnpackExt.html#method.multipeek
+pub trait Rewind: Iterator {
+    /// The position of elements taken tentatively
+    type Cursor;
+
+    /// The cursor at the current position
+    fn cursor(&mut self) -> Self::Cursor;
+
+    /// Take the element at the cursor, and move the cursor,
+    /// the iterator is not advanced
+    fn next_at(&mut self, cursor: &mut Self::Cursor) -> Option<Self::Item>;
+
+    /// Advance the iterator to the cursor
+    fn commit_to(&mut self, cursor: Self::Cursor);
+
+    /// Take elements tentatively, the iterator is advanced by [`Tentative::commit`]
+    fn tentative(&mut self) -> Tentative<'_, Self> {
+        let cursor = self.cursor();
+        Tentative { src: self, cursor }
+    }
+}
+
+/// The iterator takes elements tentatively, create by [`Rewind::tentative`]
+pub struct Tentative<'a, R: Rewind + ?Sized> {
+    src: &'a mut R,
+    cursor: R::Cursor,
+}
+
+impl<R: Rewind + ?Sized> Tentative<'_, R> {
+    /// Advance the source iterator over the elements taken
+    pub fn commit(self) {
+        self.src.commit_to(self.cursor);
+    }
+
+    /// Advance the source iterator over the first `n` elements taken
+    pub fn commit_first(self, n: usize) {
+        self.src.take(n).for_each(drop);
+    }
+}
+
+impl<R: Rewind + ?Sized> Iterator for Tentative<'_, R> {
+    type Item = R::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.src.next_at(&mut self.cursor)
+    }
+}
+
+/// An iterator adapter which can peek any number of elements,
+/// create by [`IterUnpackExt::multipeek`]
+///
+/// The peeked elements are buffered until they are taken.
+/// It implements [`Rewind`] for cloneable elements,
+/// so `iunpack!(try ...)` and `imatch!(try ...)` only take the elements of success.
+///
+/// # Examples
+/// ```
+/// # use itermacros::{iunpack, IterUnpackExt, UnpackError};
+/// let mut iter = (0..5).multipeek();
+/// assert_eq!(iter.peek(), Some(&0));
+/// assert_eq!(iter.peek(), Some(&1));
+/// assert_eq!(iter.peek_nth(3), Some(&3));
+/// iter.reset_peek();
+/// assert_eq!(iter.peek(), Some(&0));
+///
+/// iter.commit(2);
+/// assert_eq!(iter.next(), Some(2));
+///
+/// assert_eq!(iunpack!(try a = iter), Err(UnpackError::TooMany { expected: 1 }));
+/// assert_eq!(iunpack!(try a, b = iter if a > b), Err(UnpackError::MatchGuard));
+/// assert_eq!(iunpack!(try a, b = iter), Ok((3, 4)));
+/// assert_eq!(iter.next(), None);
+/// ```
+#[derive(Debug)]
+#[cfg(feature = "alloc")]
+pub struct MultiPeek<I: Iterator> {
+    iter: I,
+    buf: VecDeque<I::Item>,
+    index: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<I: Iterator> MultiPeek<I> {
+    /// Create from an iterator, like [`IterUnpackExt::multipeek`]
+    pub fn new(iter: I) -> Self {
+        Self { iter, buf: VecDeque::new(), index: 0 }
+    }
+
+    /// Peek the element at the peek cursor, and move the peek cursor
+    pub fn peek(&mut self) -> Option<&I::Item> {
+        self.fill(self.index)?;
+        self.index += 1;
+        self.buf.get(self.index - 1)
+    }
+
+    /// Peek the `n`th element, the peek cursor is not moved
+    pub fn peek_nth(&mut self, n: usize) -> Option<&I::Item> {
+        self.fill(n)
+    }
+
+    /// Reset the peek cursor to the next element
+    pub fn reset_peek(&mut self) {
+        self.index = 0;
+    }
+
+    /// Take and drop the first `n` elements, and reset the peek cursor
+    pub fn commit(&mut self, n: usize) {
+        self.index = 0;
+        let buffered = n.min(self.buf.len());
+        self.buf.drain(..buffered);
+        self.iter.by_ref().take(n - buffered).for_each(drop);
+    }
+
+    /// Buffer the `n`th element
+    fn fill(&mut self, n: usize) -> Option<&I::Item> {
+        while self.buf.len() <= n {
+            self.buf.push_back(self.iter.next()?);
+        }
+        self.buf.get(n)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I: Iterator> Iterator for MultiPeek<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.index = 0;
+        self.buf.pop_front().or_else(|| self.iter.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        let len = self.buf.len();
+        (lower.saturating_add(len), upper.and_then(|n| n.checked_add(len)))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I: Iterator> Rewind for MultiPeek<I>
+where I::Item: Clone,
+{
+    type Cursor = usize;
+
+    fn cursor(&mut self) -> Self::Cursor {
+        0
+    }
+
+    fn next_at(&mut self, cursor: &mut Self::Cursor) -> Option<Self::Item> {
+        let elem = self.fill(*cursor)?.clone();
+        *cursor += 1;
+        Some(elem)
+    }
+
+    fn commit_to(&mut self, cursor: Self::Cursor) {
+        self.commit(cursor);
+    }
+}
+
+/// Collections which can fail to extend, like [`Extend`]
+///
+/// The star patterns `*name: Type` and `**name: Type` prefer it to [`FromIterator`],
+/// the collection must also implement [`Default`],
+/// and `*&mut buf` prefers it to [`Extend`],
+/// then the failure is passed to the else branch.
+///
+/// [`Extend`]: core::iter::Extend
+/// [`FromIterator`]: core::iter::FromIterator
+pub trait TryExtend<T> {
+    /// Extend the collection with the elements of the iterator,
+    /// stop at the first element which cannot be added
+    fn try_extend<I>(&mut self, iter: I) -> Result<(), UnpackError>
+    where I: IntoIterator<Item = T>;
+}
+
+/// A vector with fixed capacity stored inline, without heap allocation
+///
+/// It implements [`TryExtend`], so a star pattern collects into it without panic,
+/// when the capacity is exceeded, the failure is [`UnpackError::Overflow`].
+///
+/// # Examples
+/// ```
+/// # use itermacros::{iunpack, FixedVec, UnpackError};
+/// assert_eq!(iunpack!(cmd, *args: FixedVec<_, 2> = ["add", "a", "b"] => {
+///     (cmd, args.len(), args[1])
+/// } else panic!()), ("add", 2, "b"));
+///
+/// assert_eq!(iunpack!(cmd, *args: FixedVec<_, 2> = ["add", "a", "b", "c"] => {
+///     panic!()
+/// } else(err) {
+///     err
+/// }), UnpackError::Overflow { capacity: 2 });
+///
+/// assert_eq!(iunpack!(a, **rest: FixedVec<_, 4>, b = 0..6 => {
+///     rest
+/// } else panic!()), [1, 2, 3, 4]);
+///
+/// let mut buf = FixedVec::<i32, 2>::new();
+/// assert_eq!(buf.try_push(1), Ok(()));
+/// assert_eq!(buf.try_push(2), Ok(()));
+/// assert_eq!(buf.try_push(3), Err(3));
+/// assert_eq!(buf.pop(), Some(2));
+/// assert_eq!(buf, [1]);
+/// ```
+pub struct FixedVec<T, const N: usize> {
+    buf: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> FixedVec<T, N> {
+    /// Create an empty vector
+    pub const fn new() -> Self {
+        Self { buf: [const { MaybeUninit::uninit() }; N], len: 0 }
+    }
+
+    /// Maximum count of elements, is `N`
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Count of elements
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The vector has no elements
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The vector has `N` elements
+    pub const fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Append an element, or return it when the vector is full
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+        self.buf[self.len].write(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove the last element
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        self.len -= 1;
+        // SAFETY: the elements before the old length are initialized,
+        // and the length is decreased, so it is not read again
+        Some(unsafe { self.buf[self.len].assume_init_read() })
+    }
+
+    /// Remove all elements
+    pub fn clear(&mut self) {
+        let elems: *mut [T] = self.as_mut_slice();
+        self.len = 0;
+        // SAFETY: the elements are initialized,
+        // and the length is cleared before dropping, so they are not dropped again
+        unsafe { ptr::drop_in_place(elems) }
+    }
+
+    /// Slice of all elements
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: the elements before the length are initialized
+        unsafe { slice::from_raw_parts(self.buf.as_ptr().cast(), self.len) }
+    }
+
+    /// Mutable slice of all elements
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: the elements before the length are initialized
+        unsafe { slice::from_raw_parts_mut(self.buf.as_mut_ptr().cast(), self.len) }
+    }
+}
+
+impl<T, const N: usize> TryExtend<T> for FixedVec<T, N> {
+    fn try_extend<I>(&mut self, iter: I) -> Result<(), UnpackError>
+    where I: IntoIterator<Item = T>,
+    {
+        for elem in iter {
+            if self.try_push(elem).is_err() {
+                return Err(UnpackError::Overflow { capacity: N });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> Drop for FixedVec<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T, const N: usize> Default for FixedVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for FixedVec<T, N> {
+    fn clone(&self) -> Self {
+        let mut vec = Self::new();
+        for elem in self.iter() {
+            let _ = vec.try_push(elem.clone());
+        }
+        vec
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for FixedVec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl<T, const N: usize> Deref for FixedVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for FixedVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a FixedVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut FixedVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_mut_slice().iter_mut()
+    }
+}
+
+impl<T: PartialEq<U>, U, const N: usize, const M: usize> PartialEq<FixedVec<U, M>>
+for FixedVec<T, N>
+{
+    fn eq(&self, other: &FixedVec<U, M>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: PartialEq<U>, U, const N: usize, const M: usize> PartialEq<[U; M]> for FixedVec<T, N> {
+    fn eq(&self, other: &[U; M]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl<T: PartialEq<U>, U, const N: usize> PartialEq<[U]> for FixedVec<T, N> {
+    fn eq(&self, other: &[U]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl<T: PartialEq<U>, U, const N: usize> PartialEq<&[U]> for FixedVec<T, N> {
+    fn eq(&self, other: &&[U]) -> bool {
+        self.as_slice() == *other
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for FixedVec<T, N> {}
+
+/// Take `N` elements into array, or the count of elements taken
+fn fill_array<I, const N: usize>(iter: &mut I) -> Result<[I::Item; N], usize>
+where I: Iterator + ?Sized,
+{
+    let mut got = 0;
+    let arr: [Option<I::Item>; N] = core::array::from_fn(|i| {
+        if got != i {
+            return None;
+        }
+        let elem = iter.next();
+        got += elem.is_some() as usize;
+        elem
+    });
+    if got != N {
+        return Err(got);
+    }
+    Ok(arr.map(Option::unwrap))
+}
 
 /// Use pattern unpack iterator
 ///
 /// The expression after the equal sign must implement [`IntoIterator`].\
 /// The final pattern may be followed by a trailing comma.
 ///
-/// Use else to handle iterator length mismatch or pattern mismatch
+/// Without `=> body else ...`, the expression is a
+/// [`Result`]`<(...), `[`UnpackError`]`>`,
+/// the tuple is built from the identifier patterns and named star patterns in order,
+/// nested patterns build nested tuples, other patterns are only checked.
+///
+/// Use else to handle iterator length mismatch or pattern mismatch,
+/// use `else(err)` to get the [`UnpackError`], it is supported by all forms.
+/// Use `else(err, consumed, rest)` also get the elements taken from the front in order
+/// into [`Vec`], and the remaining iterator followed by the elements taken from the back,
+/// so `consumed.into_iter().chain(rest)` is the original elements.
+/// It requires [`Clone`], and clones each taken element even if the match succeeds.
+/// For star forms, the `got` of [`UnpackError::TooFew`]
+/// is the count of start and end elements taken.
+/// The start and end counts are not reported separately,
+/// the `consumed` of `else(err, consumed, rest)` has the elements taken from the front.
 ///
 /// - Use `*name` pattern any elements to [`Vec`],
-///   use [`DoubleEndedIterator`] pattern end elements.
-/// - Use `*name: Type` pattern any elements collect into impl [`FromIterator`].
+///   take end elements from the back before the middle elements
+///   if the iterator is [`DoubleEndedIterator`],
+///   otherwise delay the elements by the end elements like `**name`.
+/// - Use `*name: Type` pattern any elements collect into impl [`FromIterator`],
+///   or impl [`TryExtend`] and [`Default`] like [`FixedVec`],
+///   the failure of [`TryExtend`] is passed to else
+/// - Use `*&mut buf` clear the existing collection `buf` by its `clear` method,
+///   and extend it with any elements by [`TryExtend`] or [`Extend`], it binds nothing.
+///   It also supports `**&mut buf`
 /// - Use `*=iter` pattern start and end elements, do not check iter is stopped.
 ///   Internally use the given variable name to store iterator for future use.
-/// - Use `**name` like `*name`, but use [`Iterator`]
+///   The end patterns require [`DoubleEndedIterator`]
+/// - Use `**name` like `*name`, but always delay the elements,
+///   it takes all elements even without collection.
+///   `**name: Type` has the same requirements as `*name: Type`
+/// - Use `[patterns]` unpack the element as a nested [`IntoIterator`],
+///   it supports all the above patterns, so it is not a slice pattern.
+///   The nested failure is [`UnpackError::Nested`]
+/// - Use `==expr` compare the element with the value of `expr` by [`PartialEq`],
+///   it binds nothing, and `expr` can use the bindings of previous patterns
+/// - Use `pattern if guard` check the element with the bindings in scope,
+///   the failure is [`UnpackError::Guard`]
+/// - Use `= iter if guard` check all elements after matched,
+///   the failure is [`UnpackError::MatchGuard`]
+/// - Use `*name{range}` limit the count of star pattern elements by a range of [`usize`],
+///   it stops taking elements after the maximum is exceeded,
+///   the failure is [`UnpackError::StarBounds`],
+///   empty bounds always fail with [`UnpackError::EmptyBounds`].
+///   It supports `*`, `*name`, `*name: Type` and the `**` forms
+/// - Use `*left, SEP, *right` split the middle elements at the first separator,
+///   `SEP` is a literal or `==expr`, use `last SEP` split at the last separator.
+///   Both star patterns support `*`, `*name` and `*name: Type`,
+///   the end patterns and `last SEP` require [`DoubleEndedIterator`],
+///   the failure is [`UnpackError::NoSeparator`]
+/// - Use `*name while pred` take elements while `pred(&elem)` is true,
+///   it is a start pattern, so it can be followed by start patterns and a star pattern.
+///   It takes elements before the end patterns, and uses [`Iterator`] only.
+///   It supports `*`, `*name` and `*name: Type`,
+///   its elements are not counted by [`UnpackError::TooFew`] like other star patterns
+/// - Use `name?` take an optional element into [`Option`],
+///   use `pattern = expr` match `expr` instead when the element is absent
+///
+/// Use `try patterns = iter` take elements tentatively from the [`Rewind`] iterator `iter`,
+/// like [`MultiPeek`], it is advanced over the taken elements only when matched, before the body,
+/// so it is not advanced on failure.
+/// The end patterns delay the elements like `**`, because it uses [`Iterator`] only.
+/// The `*=iter` pattern is not supported, it would borrow `iter` while the elements are committed.
 ///
-/// There may be an internal loop, please use the label to break or continue.
+/// The optional patterns must follow all required start patterns,
+/// and cannot be end patterns.
+/// They take elements after all required start and end patterns,
+/// so the star pattern only gets elements after the optional patterns are filled.
 ///
-/// [`FromIterator`]: std::iter::FromIterator
-/// [`Iterator`]: std::iter::Iterator
-/// [`IntoIterator`]: std::iter::IntoIterator
-/// [`DoubleEndedIterator`]: std::iter::DoubleEndedIterator
-/// [`Vec`]: std::vec::Vec
+/// The guard, `==expr` and default `expr` are ended by `,` or `=` at the top level,
+/// or use parentheses.
+///
+/// [`FromIterator`]: core::iter::FromIterator
+/// [`Default`]: core::default::Default
+/// [`Extend`]: core::iter::Extend
+/// [`PartialEq`]: core::cmp::PartialEq
+/// [`Iterator`]: core::iter::Iterator
+/// [`IntoIterator`]: core::iter::IntoIterator
+/// [`DoubleEndedIterator`]: core::iter::DoubleEndedIterator
+/// [`Vec`]: https://doc.rust-lang.org/alloc/vec/struct.Vec.html
+/// [`UnpackError::Nested`]: enum.UnpackError.html#variant.Nested
+/// [`MultiPeek`]: struct.MultiPeek.html
 ///
 /// # Examples
 ///
 /// Sized iterator
 /// ```
-/// # use itermacros::iunpack;
+/// # use itermacros::{iunpack, UnpackError};
 /// assert_eq!(iunpack!(a, b, c, d, e = 0..5 => {
 ///     (a, b, c, d, e)
 /// } else panic!()), (0, 1, 2, 3, 4));
@@ -35,19 +1345,26 @@
 ///     panic!()
 /// } else(err) {
 ///     err
-/// }), 3); // fail, not enough values
+/// }), UnpackError::TooFew { got: 3, expected: 5 }); // fail, not enough values
 ///
 /// assert_eq!(iunpack!(a, b, c, d, e = 0..7 => {
 ///     panic!()
 /// } else(err) {
 ///     err
-/// }), 5); // fail, too many values
+/// }), UnpackError::TooMany { expected: 5 }); // fail, too many values
+///
+/// assert_eq!(iunpack!(a, 2..=4, c = 0..3 => {
+///     panic!()
+/// } else(err) {
+///     err
+/// }), UnpackError::Mismatch { index: 1, from_back: false }); // fail, pattern mismatch
 /// ```
 ///
 /// Any size iterator
 /// ```
-/// # use itermacros::iunpack;
+/// # use itermacros::{iunpack, UnpackError};
 /// # use std::collections::HashSet;
+/// # #[cfg(feature = "alloc")] {
 /// assert_eq!(iunpack!(a, b, *c, d, e = 0..8 => {
 ///     (a, b, c, d, e)
 /// } else panic!()), (0, 1, vec![2, 3, 4, 5], 6, 7));
@@ -60,245 +1377,2475 @@
 ///     (a, b, c, d, e)
 /// } else panic!()), (0, 1, HashSet::from([2, 3, 4, 5]), 6, 7));
 ///
-/// // use Iterator, is not DoubleEndedIterator
+/// // always delay the elements, use Iterator only
 /// assert_eq!(iunpack!(a, b, **c, d, e = 0..8 => {
 ///     (a, b, c, d, e)
 /// } else panic!()), (0, 1, vec![2, 3, 4, 5], 6, 7));
 ///
+/// assert_eq!(iunpack!(a, b, **c: Box<[_]>, d, e = 0..8 => {
+///     (a, b, c, d, e)
+/// } else panic!()), (0, 1, Box::from([2, 3, 4, 5]), 6, 7));
+///
+/// // is not DoubleEndedIterator, delay the elements like `**c`
+/// assert_eq!(iunpack!(a, b, *c, d, e = (0..8).filter(|_| true) => {
+///     (a, b, c, d, e)
+/// } else panic!()), (0, 1, vec![2, 3, 4, 5], 6, 7));
+///
+/// // the body is expanded once for both ways, so it can result a closure
+/// let add = iunpack!(a, *mid, b = vec![1, 2, 3] => {
+///     assert_eq!(mid, [2]);
+///     move |x: i32| x + a + b
+/// } else panic!());
+/// assert_eq!(add(1), 5);
+///
+/// // the end elements are matched before the middle elements are taken
+/// assert_eq!(iunpack!(a, *b{..1}, 9 = 0..8),
+///            Err(UnpackError::Mismatch { index: 0, from_back: true }));
+///
 /// // no collect
 /// assert_eq!(iunpack!(a, b, *, d, e = 0..8 => {
 ///     (a, b, d, e)
 /// } else panic!()), (0, 1, 6, 7));
 ///
-/// // use Iterator, is not DoubleEndedIterator, no collect
+/// // always delay the elements, no collect
 /// assert_eq!(iunpack!(a, b, **, d, e = 0..8 => {
 ///     (a, b, d, e)
 /// } else panic!()), (0, 1, 6, 7));
-/// ```
 ///
-/// Pattern example
+/// // fails
+/// assert_eq!(iunpack!(a, b, *c, d, e = 0..3 => {
+///     panic!()
+/// } else(err) {
+///     err
+/// }), UnpackError::TooFew { got: 3, expected: 4 });
+///
+/// assert_eq!(iunpack!(a, b, **c, d, e = 0..3 => {
+///     panic!()
+/// } else(err) {
+///     err
+/// }), UnpackError::TooFew { got: 3, expected: 4 });
+///
+/// assert_eq!(iunpack!(a, b, *=c, 0, e = 0..8 => {
+///     panic!()
+/// } else(err) {
+///     err
+/// }), UnpackError::Mismatch { index: 1, from_back: true });
+///
+/// assert_eq!(iunpack!(a, b, **, 0, e = 0..8 => {
+///     panic!()
+/// } else(err) {
+///     err
+/// }), UnpackError::Mismatch { index: 1, from_back: true });
+///
+/// // break and continue are in the caller's loop
+/// let mut sum = 0;
+/// for i in 0..8 {
+///     sum += iunpack!(a, **, 1..=4 = 0..i => {
+///         if a != 0 { break }
+///         i
+///     } else continue);
+/// }
+/// assert_eq!(sum, 2 + 3 + 4 + 5);
+/// # }
+/// ```
+///
+/// Existing buffer example
 /// ```
 /// # use itermacros::iunpack;
-/// # use std::collections::HashSet;
-/// assert_eq!(iunpack!(_a, _b, 2..=10 = 0..3 => { true } else panic!()), true);
+/// let mut args = Vec::with_capacity(8);
+/// let mut lens = vec![];
+/// for line in ["add 1 2", "neg 3"] {
+///     iunpack!(_, *&mut args = line.split(' ') => {
+///         lens.push(args.len());
+///     } else panic!());
+/// }
+/// assert_eq!(lens, [2, 1]);
+/// assert_eq!(args, ["3"]);
+///
+/// assert_eq!(iunpack!(a, **&mut args, b = "x y z".split(' ')), Ok(("x", "z")));
+/// assert_eq!(args, ["y"]);
+/// ```
 ///
-/// assert_eq!(iunpack!((0 | 1 | 2), _b, _c = 0..3 => {
-///     true
-/// } else panic!()), true);
+/// Nested iterator
+/// ```
+/// # use itermacros::{iunpack, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// let rows = vec![vec![1, 2, 3], vec![4, 5]];
 ///
-/// assert_eq!(iunpack!(*, 2..=10 = 0..3 => {
-///     true
-/// } else panic!()), true);
+/// assert_eq!(iunpack!([a, *b], [c, d] = rows.clone() => {
+///     (a, b, c, d)
+/// } else panic!()), (1, vec![2, 3], 4, 5));
 ///
-/// assert_eq!(iunpack!((0 | 1 | 2), * = 0..3 => {
-///     true
-/// } else panic!()), true);
+/// assert_eq!(iunpack!(**, [_, _, [x, y]] = [[[1, 2]; 3]] => {
+///     (x, y)
+/// } else panic!()), (1, 2));
 ///
 /// // fails
-/// assert_eq!(iunpack!(_a, _b, 3..=10 = 0..3 => {
+/// assert_eq!(iunpack!([a, *b], [c, 6] = rows.clone() => {
 ///     panic!()
-/// } else true), true);
+/// } else(err) {
+///     err
+/// }), UnpackError::Nested {
+///     index: 1,
+///     from_back: false,
+///     error: Box::new(UnpackError::Mismatch { index: 1, from_back: false }),
+/// });
 ///
-/// assert_eq!(iunpack!((1 | 2), _b, _c = 0..3 => {
+/// assert_eq!(iunpack!(*, [c, d, e] = rows => {
 ///     panic!()
-/// } else true), true);
+/// } else(err) {
+///     err.to_string()
+/// }), "element 0 from the back: not enough elements, expected 3, got 2");
+/// # }
+/// ```
+///
+/// Expression form
+/// ```
+/// # use itermacros::{iunpack, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// assert_eq!(iunpack!(a, *b, c = 0..5), Ok((0, vec![1, 2, 3], 4)));
+/// assert_eq!(iunpack!(a, _, [b, c], *, [5, _] = [[0; 2], [1; 2], [2; 2], [3; 2], [4; 2]]),
+///            Err(UnpackError::Nested {
+///                index: 0,
+///                from_back: true,
+///                error: Box::new(UnpackError::Mismatch { index: 0, from_back: false }),
+///            }));
+/// assert_eq!(iunpack!(a, _, [b, c], **rest, [4, _] = [[0; 2], [1; 2], [2; 2], [3; 2], [4; 2]]),
+///            Ok(([0; 2], (2, 2), vec![[3; 2]], ())));
+/// assert_eq!(iunpack!(_, 1 = 0..2), Ok(()));
+///
+/// fn parse(s: &str) -> Result<(i32, i32), UnpackError> {
+///     let (a, b) = iunpack!(a, b = s.split(',').map(|s| s.parse().unwrap()))?;
+///     Ok((a, b))
+/// }
+/// assert_eq!(parse("1,2"), Ok((1, 2)));
+/// assert_eq!(parse("1,2,3"), Err(UnpackError::TooMany { expected: 2 }));
+/// # }
+/// ```
+///
+/// Recover example
+/// ```
+/// # use itermacros::{iunpack, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// let tokens = "let x = 1".split(' ');
+/// let stmt = iunpack!("fn", name, *args = tokens => {
+///     format!("fn {name} {args:?}")
+/// } else(err, consumed, rest) {
+///     assert_eq!(err, UnpackError::Mismatch { index: 0, from_back: false });
+///     assert_eq!(consumed, ["let"]);
+///     iunpack!("let", name, "=", value = consumed.into_iter().chain(rest) => {
+///         format!("let {name} {value}")
+///     } else panic!())
+/// });
+/// assert_eq!(stmt, "let x 1");
 ///
-/// assert_eq!(iunpack!(_a, **, 3..=10 = 0..3 => {
+/// let elems = iunpack!(a, *, 9, 5 = vec![1, 2, 3, 4, 5] => {
 ///     panic!()
-/// } else true), true);
+/// } else(err, consumed, rest) {
+///     assert_eq!(err, UnpackError::Mismatch { index: 1, from_back: true });
+///     assert_eq!(consumed, [1]);
+///     consumed.into_iter().chain(rest).collect::<Vec<_>>()
+/// });
+/// assert_eq!(elems, [1, 2, 3, 4, 5]);
+/// # }
+/// ```
+///
+/// Transactional example
+/// ```
+/// # use itermacros::{iunpack, IterUnpackExt, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// let mut tokens = "let x = 1 ; y".split(' ').multipeek();
+/// assert_eq!(iunpack!(try "fn", name, * = tokens),
+///            Err(UnpackError::Mismatch { index: 0, from_back: false }));
+/// assert_eq!(tokens.peek(), Some(&"let"));
+///
+/// assert_eq!(iunpack!(try "let", name, "=", value, ";", * = tokens => {
+///     (name, value)
+/// } else panic!()), ("x", "1"));
+/// assert_eq!(tokens.next(), Some("y"));
+///
+/// let mut iter = [1, 2, 3].into_iter().multipeek();
+/// assert_eq!(iunpack!(try a, b, c = iter if a > b), Err(UnpackError::MatchGuard));
+/// assert_eq!(iter.next(), Some(1));
+/// # }
+/// ```
 ///
-/// assert_eq!(iunpack!((1 | 2), * = 0..3 => {
+/// Guard example
+/// ```
+/// # use itermacros::{iunpack, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// assert_eq!(iunpack!(a, b if b > a, *rest = [1, 2, 3]), Ok((1, 2, vec![3])));
+/// assert_eq!(iunpack!(a, b if b > a, *rest = [2, 1, 3]),
+///            Err(UnpackError::Guard { index: 1, from_back: false }));
+///
+/// assert_eq!(iunpack!(len, *rest = [2, 5, 6] if len == rest.len() => {
+///     rest
+/// } else panic!()), [5, 6]);
+///
+/// assert_eq!(iunpack!(len, *rest = [3, 5, 6] if len == rest.len() => {
 ///     panic!()
-/// } else true), true);
+/// } else(err) {
+///     err
+/// }), UnpackError::MatchGuard);
+/// # }
+/// ```
+///
+/// Value equality example
+/// ```
+/// # use itermacros::{iunpack, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// let header = String::from("name");
+/// assert_eq!(iunpack!(==header, *rest = ["name", "a", "b"]), Ok((vec!["a", "b"],)));
+/// assert_eq!(iunpack!(==header, *rest = ["id", "a", "b"]),
+///            Err(UnpackError::Mismatch { index: 0, from_back: false }));
+///
+/// // back reference
+/// assert_eq!(iunpack!(x, *, ==x = [1, 2, 1]), Ok((1,)));
+/// assert_eq!(iunpack!(x, **, ==x + 1 = [1, 2, 3]),
+///            Err(UnpackError::Mismatch { index: 0, from_back: true }));
+/// # }
+/// ```
+///
+/// Star bounds example
+/// ```
+/// # use itermacros::{iunpack, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// assert_eq!(iunpack!(cmd, *args{1..3} = ["add", "a", "b"]), Ok(("add", vec!["a", "b"])));
+/// assert_eq!(iunpack!(cmd, *args{1..3} = ["add"]),
+///            Err(UnpackError::StarBounds { got: 0, min: 1, max: Some(2) }));
+/// assert_eq!(iunpack!(len, *args{..=len} = [1, 5, 6]),
+///            Err(UnpackError::StarBounds { got: 2, min: 0, max: Some(1) }));
+/// let len = 0;
+/// assert_eq!(iunpack!(*args{..len} = [1]), Err(UnpackError::EmptyBounds));
+///
+/// // stop on infinite iterator
+/// assert_eq!(iunpack!(a, **{..4}, b = 0..),
+///            Err(UnpackError::StarBounds { got: 4, min: 0, max: Some(3) }));
+/// # }
+/// ```
+///
+/// Separator example
+/// ```
+/// # use itermacros::{iunpack, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// let args = ["run", "-v", "--", "a", "--", "b"];
+/// assert_eq!(iunpack!(cmd, *opts, "--", *rest = args),
+///            Ok(("run", vec!["-v"], vec!["a", "--", "b"])));
+/// assert_eq!(iunpack!(cmd, *opts, last "--", *rest = args),
+///            Ok(("run", vec!["-v", "--", "a"], vec!["b"])));
+///
+/// assert_eq!(iunpack!(*key: String, '=', *value: String = "a=b=c".chars()),
+///            Ok(("a".to_owned(), "b=c".to_owned())));
+///
+/// let sep = 0;
+/// assert_eq!(iunpack!(*, ==sep, * = [1, 2, 3]), Err(UnpackError::NoSeparator));
+/// # }
+/// ```
+///
+/// Take-while example
+/// ```
+/// # use itermacros::{iunpack, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// let args = ["-a", "-b", "run", "x", "y"];
+/// assert_eq!(iunpack!(*flags while |s| s.starts_with('-'), cmd, *args = args),
+///            Ok((vec!["-a", "-b"], "run", vec!["x", "y"])));
+///
+/// assert_eq!(iunpack!(a, * while |&x| x < 5, b, **, c = 0..8), Ok((0, 5, 7)));
+/// assert_eq!(iunpack!(a, * while |&x| x < 5, b, c = 0..6),
+///            Err(UnpackError::TooFew { got: 2, expected: 3 }));
+/// # }
+/// ```
+///
+/// Optional example
+/// ```
+/// # use itermacros::{iunpack, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// assert_eq!(iunpack!(a, b?, c = 0 = [1, 2, 3]), Ok((1, Some(2), 3)));
+/// assert_eq!(iunpack!(a, b?, c = 0 = [1, 2]), Ok((1, Some(2), 0)));
+/// assert_eq!(iunpack!(a, b?, c = 0 = [1]), Ok((1, None, 0)));
+/// assert_eq!(iunpack!(a, b?, c = 0 = []), Err(UnpackError::TooFew { got: 0, expected: 1 }));
+/// assert_eq!(iunpack!(a, b?, c = 0 = [1, 2, 3, 4]), Err(UnpackError::TooMany { expected: 3 }));
+///
+/// // end patterns take elements first
+/// assert_eq!(iunpack!(a, b?, *mid, c = [1, 2]), Ok((1, None, vec![], 2)));
+/// assert_eq!(iunpack!(a, b?, *mid, c = [1, 2, 3, 4]), Ok((1, Some(2), vec![3], 4)));
+/// assert_eq!(iunpack!(a, b = 0, **mid, c = [1, 2]), Ok((1, 0, vec![], 2)));
+/// assert_eq!(iunpack!(a, b = 0, **mid, c = [1, 2, 3, 4]), Ok((1, 2, vec![3], 4)));
+/// assert_eq!(iunpack!(a?, b?, **, c, d = [1, 2, 3]), Ok((Some(1), None, 2, 3)));
+/// # }
+/// ```
+///
+/// No std example, the forms without collection or with [`FixedVec`] only use [`core`]
+/// ```
+/// #![no_std]
+/// # extern crate std as _std;
+/// use itermacros::{ilet, iunpack, FixedVec, UnpackError};
+///
+/// fn main() {
+///     assert_eq!(iunpack!(a, b = [1, 2]), Ok((1, 2)));
+///     assert_eq!(iunpack!(a, * = 0..), Ok((0,)));
+///     assert_eq!(iunpack!(a, *, b = 0..5), Ok((0, 4)));
+///     assert_eq!(iunpack!(a, **, b = 0..5), Ok((0, 4)));
+///     assert_eq!(iunpack!(a, *=rest, b = 0..5 => {
+///         (a, rest.sum::<i32>(), b)
+///     } else panic!()), (0, 6, 4));
+///
+///     ilet!(a, ** = 0..5 else { panic!() });
+///     assert_eq!(a, 0);
+///     ilet!(a, *rest: FixedVec<_, 4>, b = 0..5 else { panic!() });
+///     assert_eq!((a, rest.as_slice(), b), (0, &[1, 2, 3][..], 4));
+///     assert_eq!(iunpack!(a, b, c = 0..2), Err(UnpackError::TooFew { got: 2, expected: 3 }));
+/// }
+/// ```
+///
+/// Pattern example
+/// ```
+/// # use itermacros::imatches;
+/// assert!(imatches!(0..3, a, b, 2..=10));
+/// assert!(imatches!(0..3, (0 | 1 | 2), b, c));
+/// assert!(imatches!(0..3, *, 2..=10));
+/// assert!(imatches!(0..3, (0 | 1 | 2), *));
+///
+/// // fails
+/// assert!(!imatches!(0..3, a, b, 3..=10));
+/// assert!(!imatches!(0..3, (1 | 2), b, c));
+/// assert!(!imatches!(0..3, a, **, 3..=10));
+/// assert!(!imatches!(0..3, (1 | 2), *));
+/// ```
+///
+/// Many patterns example, each pattern only takes a few levels of macro recursion
+/// ```
+/// # use itermacros::iunpack;
+/// assert_eq!(iunpack!(
+///     x0, x1, x2, x3, x4, x5, x6, x7, x8, x9,
+///     x10, x11, x12, x13, x14, x15, x16, x17, x18, x19,
+///     _, _, _, _, _, _, _, _, _, _,
+///     _, _, _, _, _, _, _, _, _, _,
+///     40, 41, 42, 43, 44, 45, 46, 47, 48, 49,
+///     50, 51, 52, 53, 54, 55, 56, 57, 58, 59,
+///     last
+///     = 0..61 => {
+///         (x0, x19, last)
+///     } else panic!()), (0, 19, 60));
+///
+/// // the guards are found by the delimiter, not token by token
+/// assert_eq!(iunpack!(
+///     a0, a1 if a1 > a0, a2 if a2 > a1, a3 if a3 > a2, a4 if a4 > a3,
+///     ==5, ==6, ==7, ==8, ==9,
+///     b0, b1, b2, b3, b4, b5, b6, b7, b8, b9,
+///     c0 if c0 > a0 && c0 > a1 && c0 > a2 && c0 > a3 && c0 > a4
+///         && c0 > b0 && c0 > b1 && c0 > b2 && c0 > b3 && c0 > b4
+///         && c0 > b5 && c0 > b6 && c0 > b7 && c0 > b8 && c0 > b9,
+///     c1, c2, c3, c4, c5, c6, c7,
+///     d0 = 0, d1 = 0 if d1 > c7
+///         && d1 > a0 && d1 > a1 && d1 > a2 && d1 > a3 && d1 > a4
+///         && d1 > b0 && d1 > b1 && d1 > b2 && d1 > b3 && d1 > b4
+///         && d1 > b5 && d1 > b6 && d1 > b7 && d1 > b8 && d1 > b9
+///     = 0..30 => {
+///         (a4, b9, c0, d0, d1)
+///     } else panic!()), (4, 19, 20, 28, 29));
+/// ```
+///
+/// Nested invocations example, each invocation only takes a few levels of macro recursion
+/// ```
+/// # use itermacros::iunpack;
+/// # #[cfg(feature = "alloc")] {
+/// macro_rules! sum {
+///     () => (0);
+///     (_ $($rest:tt)*) => {
+///         iunpack!(a, b, c = [1, 2, 3] => { a + b + c + sum!($($rest)*) } else 0)
+///     };
+///     (* $($rest:tt)*) => {
+///         iunpack!(a, *mid, b, c = vec![1, 2, 3, 4] => {
+///             a + mid.len() + b + c + sum!($($rest)*)
+///         } else 0)
+///     };
+/// }
+/// assert_eq!(sum!(_ _ _ _ _ _ _ _ _ _ _ _), 72);
+/// assert_eq!(sum!(* * * * * * * *), 72);
+/// assert_eq!(sum!(_ * _ * _ * _ * _ *), 75);
+/// # }
 /// ```
 #[macro_export]
 macro_rules! iunpack {
     (@if($($t:tt)*) else $($f:tt)*) => ($($t)*);
     (@if else $($f:tt)*) => ($($f)*);
-    {@revpat_do_iter_back($iter:ident, $body:block, $errbody:expr)
-        $(($used:pat) $(($pat:pat))*)?
+    (@count $($t:tt)*) => (0usize $(+ $crate::iunpack!(@if(1) else $t))*);
+    (@count_req $($t:tt)*) => (0usize $(+ $crate::iunpack!(@req $t))*);
+    (@req (while $($t:tt)*)) => (0);
+    (@req $t:tt) => (1);
+    (@fail($e:expr) [@nest($index:expr, $from_back:expr) $else:tt]) => {
+        $crate::iunpack!(@fail($crate::UnpackError::Nested {
+            index: $index,
+            from_back: $from_back,
+            error: $crate::__private::Box::new($e),
+        }) $else)
+    };
+    (@fail($e:expr) [($err:ident) $errbody:block]) => {{
+        let $err = $e;
+        $errbody
+    }};
+    (@fail($e:expr) [$errbody:expr]) => ($errbody);
+
+    // parse patterns
+    {@parse $cfg:tt [$($e:tt)*] = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)*] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*]} => {
+        $crate::iunpack!(@done $cfg [$($e)*])
+    };
+    {@parse $cfg:tt [$($e:tt)*] [$($sub:tt)*] $($rest:tt)*} => {
+        $crate::iunpack!(@parse(sub $cfg [$($e)*] $($rest)*) [] $($sub)*)
+    };
+    // take the single token patterns four at a time, except the sub patterns
+    {@parse $cfg:tt [$($e:tt)*] $a:tt, [$($sub:tt)*] $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* ($a)] [$($sub)*] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $a:tt, $b:tt, [$($sub:tt)*] $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* ($a) ($b)] [$($sub)*] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $a:tt, $b:tt, $c:tt, [$($sub:tt)*] $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* ($a) ($b) ($c)] [$($sub)*] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $a:tt, $b:tt, $c:tt, $d:tt, $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* ($a) ($b) ($c) ($d)] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $a:tt, * $mid:ident , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* ($a) (* $mid)] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $a:tt, * $mid:ident = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* ($a) (* $mid)] $($rest)*)
+    };
+    // the last patterns before `=`, scan the default value or the iterator directly
+    {@parse $cfg:tt [$($e:tt)*] $a:tt, $b:tt, $c:tt, $d:ident = $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @default $cfg [$($e)* ($a) ($b) ($c)] ($d)) [] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $a:tt, $b:tt, $c:ident = $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @default $cfg [$($e)* ($a) ($b)] ($c)) [] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $a:tt, $b:ident = $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @default $cfg [$($e)* ($a)] ($b)) [] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] == $x:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (== [$x])] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] == $($rest:tt)+} => {
+        $crate::iunpack!(@eq $cfg [$($e)*] [] $($rest)+)
+    };
+    {@parse $cfg:tt [$($e:tt)*] last $sep:literal $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (last ($sep)) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] last == $x:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (last (== [$x]))] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] last == $($rest:tt)+} => {
+        $crate::iunpack!(@eq(last $cfg) [$($e)*] [] $($rest)+)
+    };
+    {@parse(try) [$($e:tt)*] *=$mid:ident $($rest:tt)*} => {
+        ::core::compile_error!("`*=iter` pattern is not supported by `iunpack!(try ...)`")
+    };
+    {@parse $cfg:tt [$($e:tt)*] *=$mid:ident $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (*= $mid) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] ** &mut $($rest:tt)+} => {
+        $crate::iunpack!(@star_mut $cfg [$($e)*] (**) [] $($rest)+)
+    };
+    {@parse $cfg:tt [$($e:tt)*] ** $mid:ident : $ty:ty {$($b:tt)*} $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (** $mid: $ty {$($b)*}) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] ** $mid:ident : $ty:ty , $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (** $mid: $ty) , $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] ** $mid:ident : $ty:ty = $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (** $mid: $ty) = $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] ** $mid:ident : $ty:ty} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (** $mid: $ty))
+    };
+    {@parse $cfg:tt [$($e:tt)*] ** $mid:ident , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (** $mid)] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] ** $mid:ident $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (** $mid) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] ** $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (**) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * &mut $($rest:tt)+} => {
+        $crate::iunpack!(@star_mut $cfg [$($e)*] (*) [] $($rest)+)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * $mid:ident : $ty:ty {$($b:tt)*} $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (* $mid: $ty {$($b)*}) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * $mid:ident : $ty:ty , $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (* $mid: $ty) , $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * $mid:ident : $ty:ty = $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (* $mid: $ty) = $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * $mid:ident : $ty:ty} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (* $mid: $ty))
+    };
+    {@parse $cfg:tt [$($e:tt)*] * $mid:ident : $($rest:tt)+} => {
+        $crate::iunpack!(@star_ty $cfg [$($e)*] $mid [] $($rest)+)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * while $($rest:tt)+} => {
+        $crate::iunpack!(@while $cfg [$($e)*] (*) [] $($rest)+)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * $mid:ident = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* (* $mid)] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * $mid:ident , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (* $mid)] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * $mid:ident $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (* $mid) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* (*)] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] * $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (*) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] mut $name:ident ? $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (? (mut $name)) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $name:ident ? $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (? ($name)) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] _ ? $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (? (_)) $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] mut $name:ident if $guard:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (if (mut $name) [$guard])] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] mut $name:ident = $default:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (= (mut $name) [$default])] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $name:ident if $guard:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (if ($name) [$guard])] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $name:ident = $default:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (= ($name) [$default])] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] mut $name:ident , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (mut $name)] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] mut $name:ident = $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @default $cfg [$($e)*] (mut $name)) [] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] mut $name:ident if $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (mut $name) if $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] mut $name:ident} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (mut $name))
+    };
+    {@parse $cfg:tt [$($e:tt)*] $name:ident , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* ($name)] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $name:ident = $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @default $cfg [$($e)*] ($name)) [] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $name:ident if $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($name) if $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $name:ident} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($name))
+    };
+    {@parse $cfg:tt [$($e:tt)*] $lit:literal , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* ($lit)] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $lit:literal = $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($lit) = $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $lit:literal if $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($lit) if $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $lit:literal} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($lit))
+    };
+    {@parse $cfg:tt [$($e:tt)*] $pat:pat if $guard:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (if ($pat) [$guard])] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $pat:pat = $default:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (= ($pat) [$default])] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $pat:pat , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* ($pat)] $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $pat:pat = $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($pat) = $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $pat:pat if $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($pat) if $($rest)*)
+    };
+    {@parse $cfg:tt [$($e:tt)*] $pat:pat} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($pat))
+    };
+    {@sep(last $cfg:tt) [$($e:tt)*] $elem:tt $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (last $elem) $($rest)*)
+    };
+    {@sep $cfg:tt [$($e:tt)*] (* $($star:tt)*) {$($b:tt)*} $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (* $($star)* {$($b)*}) $($rest)*)
+    };
+    {@sep $cfg:tt [$($e:tt)*] (* $($star:tt)*) while $($rest:tt)+} => {
+        $crate::iunpack!(@while $cfg [$($e)*] (* $($star)*) [] $($rest)+)
+    };
+    {@sep $cfg:tt [$($e:tt)*] $elem:tt , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* $elem] $($rest)*)
+    };
+    {@sep $cfg:tt [$($e:tt)*] (* $($star:tt)*) = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* (* $($star)*)] $($rest)*)
+    };
+    {@sep $cfg:tt [$($e:tt)*] (*= $mid:ident) = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* (*= $mid)] $($rest)*)
+    };
+    {@sep $cfg:tt [$($e:tt)*] (? $elem:tt) = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* (? $elem)] $($rest)*)
+    };
+    {@sep $cfg:tt [$($e:tt)*] (== $x:tt) = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* (== $x)] $($rest)*)
+    };
+    {@sep $cfg:tt [$($e:tt)*] $elem:tt = $($rest:tt)*} => {
+        $crate::iunpack!(@default $cfg [$($e)*] $elem [] $($rest)*)
+    };
+    {@sep $cfg:tt [$($e:tt)*] $elem:tt if $($rest:tt)*} => {
+        $crate::iunpack!(@guard $cfg [$($e)*] $elem [] $($rest)*)
+    };
+    {@sep $cfg:tt [$($e:tt)*] $elem:tt} => {
+        $crate::iunpack!(@done $cfg [$($e)* $elem])
+    };
+    {@default $cfg:tt [$($e:tt)*] $elem:tt [] $default:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (= $elem [$default])] $($rest)*)
+    };
+    {@default $cfg:tt [$($e:tt)*] $elem:tt [$($d:tt)+] , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (= $elem [$($d)+])] $($rest)*)
+    };
+    {@default $cfg:tt [$($e:tt)*] $elem:tt [$($d:tt)+] = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* (= $elem [$($d)+])] $($rest)*)
+    };
+    {@default(sub $($s:tt)*) [$($e:tt)*] $elem:tt [$($d:tt)+]} => {
+        $crate::iunpack!(@done(sub $($s)*) [$($e)* (= $elem [$($d)+])])
+    };
+    {@default $cfg:tt [$($e:tt)*] $elem:tt [$($d:tt)+] => $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* $elem] $($d)+ => $($rest)*)
+    };
+    {@default $cfg:tt [$($e:tt)*] $elem:tt [$($d:tt)+] if $($rest:tt)*} => {
+        $crate::iunpack!(@default_guard $cfg [$($e)*] $elem [$($d)+] [] $($rest)*)
+    };
+    {@default(let) [$($e:tt)*] $elem:tt [$($d:tt)+] else $($rest:tt)+} => {
+        $crate::ilet!(@iter[$($e)* $elem][$($d)+] else $($rest)+)
+    };
+    {@default $cfg:tt [$($e:tt)*] $elem:tt [$($d:tt)+] else $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* $elem] $($d)+ else $($rest)*)
+    };
+    {@default $cfg:tt [$($e:tt)*] $elem:tt [$($d:tt)+]} => {
+        $crate::iunpack!(@tail $cfg [$($e)* $elem] $($d)+)
+    };
+    {@default $cfg:tt [$($e:tt)*] $elem:tt [$($d:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @default $cfg [$($e)*] $elem) [$($d)* $t] $($rest)*)
+    };
+    {@default_guard $cfg:tt [$($e:tt)*] $elem:tt $d:tt [$($g:tt)+] , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (if (= $elem $d) [$($g)+])] $($rest)*)
+    };
+    {@default_guard $cfg:tt [$($e:tt)*] $elem:tt $d:tt [$($g:tt)+] = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* (if (= $elem $d) [$($g)+])] $($rest)*)
+    };
+    {@default_guard(sub $($s:tt)*) [$($e:tt)*] $elem:tt $d:tt [$($g:tt)+]} => {
+        $crate::iunpack!(@done(sub $($s)*) [$($e)* (if (= $elem $d) [$($g)+])])
+    };
+    {@default_guard $cfg:tt [$($e:tt)*] $elem:tt [$($d:tt)+] [$($g:tt)+]
+        $(=> $($rest:tt)*)?
     } => {
-        $crate::iunpack!(@if$((
-            $crate::iunpack!(
-                @revpat_do_iter_back($iter, {
-                    if let ::core::option::Option::Some($used)
-                    = ::core::iter::DoubleEndedIterator::next_back(&mut $iter)
-                    {
-                        $body
-                    } else {
-                        $errbody
-                    }
-                }, $errbody) $(($pat))*
-            )
-        ))? else $body)
+        $crate::iunpack!(@tail $cfg [$($e)* $elem] $($d)+ if $($g)+ $(=> $($rest)*)?)
+    };
+    {@default_guard(let) [$($e:tt)*] $elem:tt [$($d:tt)+] [$($g:tt)+] else $($rest:tt)+} => {
+        $crate::ilet!(@iter[$($e)* $elem][$($d)+ if $($g)+] else $($rest)+)
+    };
+    {@default_guard $cfg:tt [$($e:tt)*] $elem:tt [$($d:tt)+] [$($g:tt)+] else $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* $elem] $($d)+ if $($g)+ else $($rest)*)
+    };
+    {@default_guard $cfg:tt [$($e:tt)*] $elem:tt $d:tt [$($g:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @default_guard $cfg [$($e)*] $elem $d) [$($g)* $t] $($rest)*)
+    };
+    {@guard $cfg:tt [$($e:tt)*] $elem:tt [] $guard:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (if $elem [$guard])] $($rest)*)
+    };
+    {@guard $cfg:tt [$($e:tt)*] $elem:tt [$($g:tt)+] , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (if $elem [$($g)+])] $($rest)*)
+    };
+    {@guard $cfg:tt [$($e:tt)*] $elem:tt [$($g:tt)+] = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* (if $elem [$($g)+])] $($rest)*)
+    };
+    {@guard $cfg:tt [$($e:tt)*] $elem:tt [$($g:tt)+]} => {
+        $crate::iunpack!(@done $cfg [$($e)* (if $elem [$($g)+])])
+    };
+    {@guard $cfg:tt [$($e:tt)*] $elem:tt [$($g:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @guard $cfg [$($e)*] $elem) [$($g)* $t] $($rest)*)
+    };
+    {@star_mut $cfg:tt [$($e:tt)*] ($($s:tt)+) [$($p:tt)+] , $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($($s)+ &mut [$($p)+]) , $($rest)*)
+    };
+    {@star_mut $cfg:tt [$($e:tt)*] ($($s:tt)+) [$($p:tt)+] = $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($($s)+ &mut [$($p)+]) = $($rest)*)
+    };
+    {@star_mut $cfg:tt [$($e:tt)*] ($($s:tt)+) [$($p:tt)+] {$($b:tt)*} $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($($s)+ &mut [$($p)+]) {$($b)*} $($rest)*)
+    };
+    {@star_mut $cfg:tt [$($e:tt)*] ($($s:tt)+) [$($p:tt)+] while $($rest:tt)+} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($($s)+ &mut [$($p)+]) while $($rest)+)
+    };
+    {@star_mut $cfg:tt [$($e:tt)*] ($($s:tt)+) [$($p:tt)+]} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ($($s)+ &mut [$($p)+]))
+    };
+    {@star_mut $cfg:tt [$($e:tt)*] $s:tt [$($p:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@star_mut $cfg [$($e)*] $s [$($p)* $t] $($rest)*)
+    };
+    {@star_ty $cfg:tt [$($e:tt)*] $mid:ident [$($ty:tt)+] while $($rest:tt)+} => {
+        $crate::iunpack!(@while $cfg [$($e)*] (* $mid: $($ty)+) [] $($rest)+)
+    };
+    {@star_ty $cfg:tt [$($e:tt)*] $mid:ident [$($ty:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@star_ty $cfg [$($e)*] $mid [$($ty)* $t] $($rest)*)
+    };
+    {@while $cfg:tt [$($e:tt)*] $star:tt [] $pred:expr , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (while $star [$pred])] $($rest)*)
+    };
+    {@while $cfg:tt [$($e:tt)*] $star:tt [$($p:tt)+] , $($rest:tt)*} => {
+        $crate::iunpack!(@parse $cfg [$($e)* (while $star [$($p)+])] $($rest)*)
+    };
+    {@while $cfg:tt [$($e:tt)*] $star:tt [$($p:tt)+] = $($rest:tt)*} => {
+        $crate::iunpack!(@tail $cfg [$($e)* (while $star [$($p)+])] $($rest)*)
+    };
+    {@while $cfg:tt [$($e:tt)*] $star:tt [$($p:tt)+]} => {
+        $crate::iunpack!(@done $cfg [$($e)* (while $star [$($p)+])])
+    };
+    {@while $cfg:tt [$($e:tt)*] $star:tt [$($p:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @while $cfg [$($e)*] $star) [$($p)* $t] $($rest)*)
+    };
+    {@eq $cfg:tt [$($e:tt)*] [$($x:tt)+] , $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (== [$($x)+]) , $($rest)*)
+    };
+    {@eq $cfg:tt [$($e:tt)*] [$($x:tt)+] = $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (== [$($x)+]) = $($rest)*)
+    };
+    {@eq $cfg:tt [$($e:tt)*] [$($x:tt)+] if $($rest:tt)*} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (== [$($x)+]) if $($rest)*)
+    };
+    {@eq $cfg:tt [$($e:tt)*] [$($x:tt)+]} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] (== [$($x)+]))
+    };
+    {@eq $cfg:tt [$($e:tt)*] [$($x:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @eq $cfg [$($e)*]) [$($x)* $t] $($rest)*)
+    };
+    {@tail(top) [$($e:tt)*] $iter:expr => $body:block
+        else($err:ident, $consumed:ident, $rest:ident) $errbody:block
+    } => {{
+        let mut __src = $crate::__private::Recover::new($iter);
+        $crate::iunpack!(@tail(top) [$($e)*] &mut __src => $body else($err) {
+            let ($consumed, $rest) = $crate::__private::Recover::into_parts(__src);
+            $errbody
+        })
+    }};
+    {@tail(top) [$($e:tt)*] $iter:expr => $body:block else $($else:tt)+} => {
+        $crate::iunpack!(@split(unpack($iter), $body, [$($else)+]) [] [] $($e)*)
+    };
+    {@tail(top) [$($e:tt)*] $iter:expr} => {
+        $crate::iunpack!(@split(unpack($iter), {
+            ::core::result::Result::Ok($crate::iunpack!(@tuple()[] $($e)*))
+        }, [(__err) {
+            ::core::result::Result::Err(__err)
+        }]) [] [] $($e)*)
+    };
+    {@tail(top) [$($e:tt)*] $($rest:tt)+} => {
+        $crate::iunpack!(@tail_iter [$($e)*] [] $($rest)+)
+    };
+    {@tail_iter [$($e:tt)*] [$($iter:tt)+] if $($rest:tt)+} => {
+        $crate::iunpack!(@tail_guard [$($e)*] ($($iter)+) [] $($rest)+)
+    };
+    {@tail_iter [$($e:tt)*] [$($iter:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @tail_iter [$($e)*]) [$($iter)* $t] $($rest)*)
+    };
+    {@tail_guard [$($e:tt)*] $iter:tt [] $guard:expr => $($rest:tt)+} => {
+        $crate::iunpack!(@tail_guard [$($e)*] $iter [$guard] => $($rest)+)
+    };
+    {@tail_guard [$($e:tt)*] $iter:tt [] $guard:expr} => {
+        $crate::iunpack!(@tail_guard [$($e)*] $iter [$guard])
+    };
+    {@tail_guard [$($e:tt)*] ($iter:expr) [$($g:tt)+]
+        => $body:block else($err:ident, $consumed:ident, $rest:ident) $errbody:block
+    } => {{
+        let mut __src = $crate::__private::Recover::new($iter);
+        $crate::iunpack!(@tail_guard [$($e)*] (&mut __src) [$($g)+] => $body else($err) {
+            let ($consumed, $rest) = $crate::__private::Recover::into_parts(__src);
+            $errbody
+        })
+    }};
+    {@tail_guard [$($e:tt)*] ($iter:expr) [$($g:tt)+]
+        => $body:block else $($else:tt)+
+    } => {
+        $crate::iunpack!(@split(unpack($iter), {
+            $crate::iunpack!(@guarded[$($g)+] $body [$($else)+])
+        }, [$($else)+]) [] [] $($e)*)
+    };
+    {@tail_guard [$($e:tt)*] ($iter:expr) [$($g:tt)+]} => {
+        $crate::iunpack!(@split(unpack($iter), {
+            $crate::iunpack!(@guarded[$($g)+] {
+                ::core::result::Result::Ok($crate::iunpack!(@tuple()[] $($e)*))
+            } [(__err) {
+                ::core::result::Result::Err(__err)
+            }])
+        }, [(__err) {
+            ::core::result::Result::Err(__err)
+        }]) [] [] $($e)*)
+    };
+    {@tail_guard [$($e:tt)*] $iter:tt [$($g:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @tail_guard [$($e)*] $iter) [$($g)* $t] $($rest)*)
+    };
+    {@guarded[$guard:expr] $body:block $else:tt} => {
+        if $guard {
+            $body
+        } else {
+            $crate::iunpack!(@fail($crate::UnpackError::MatchGuard) $else)
+        }
+    };
+    {@tail(try) [$($e:tt)*] $($rest:tt)+} => {
+        $crate::iunpack!(@try_iter [$($e)*] [] $($rest)+)
+    };
+    {@try_iter [$($e:tt)*] [] $iter:expr => $body:block else $($else:tt)+} => {
+        $crate::iunpack!(@try_emit [$($e)*] ($iter) [] $body [$($else)+])
+    };
+    {@try_iter [$($e:tt)*] [] $iter:expr} => {
+        $crate::iunpack!(@try_expr [$($e)*] ($iter) [])
+    };
+    {@try_iter [$($e:tt)*] [$($iter:tt)+] => $body:block else $($else:tt)+} => {
+        $crate::iunpack!(@try_emit [$($e)*] ($($iter)+) [] $body [$($else)+])
+    };
+    {@try_iter [$($e:tt)*] [$($iter:tt)+] if $($rest:tt)+} => {
+        $crate::iunpack!(@try_guard [$($e)*] ($($iter)+) [] $($rest)+)
+    };
+    {@try_iter [$($e:tt)*] [$($iter:tt)+]} => {
+        $crate::iunpack!(@try_expr [$($e)*] ($($iter)+) [])
+    };
+    {@try_iter [$($e:tt)*] [$($iter:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @try_iter [$($e)*]) [$($iter)* $t] $($rest)*)
+    };
+    {@try_guard [$($e:tt)*] $iter:tt [] $guard:expr => $body:block else $($else:tt)+} => {
+        $crate::iunpack!(@try_emit [$($e)*] $iter [if $guard] $body [$($else)+])
+    };
+    {@try_guard [$($e:tt)*] $iter:tt [] $guard:expr} => {
+        $crate::iunpack!(@try_expr [$($e)*] $iter [if $guard])
+    };
+    {@try_guard [$($e:tt)*] $iter:tt [$($g:tt)+] => $body:block else $($else:tt)+} => {
+        $crate::iunpack!(@try_emit [$($e)*] $iter [if $($g)+] $body [$($else)+])
+    };
+    {@try_guard [$($e:tt)*] $iter:tt [$($g:tt)+]} => {
+        $crate::iunpack!(@try_expr [$($e)*] $iter [if $($g)+])
+    };
+    {@try_guard [$($e:tt)*] $iter:tt [$($g:tt)*] $t:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan(iunpack @try_guard [$($e)*] $iter) [$($g)* $t] $($rest)*)
+    };
+    {@try_emit [$($e:tt)*] ($($iter:tt)+) [$($g:tt)*] $body:block [$($else:tt)+]} => {{
+        use $crate::Rewind as _;
+        let mut __tentative = ($($iter)+).tentative();
+        $crate::iunpack!(@tail(top) [$($e)*] &mut __tentative $($g)* => {
+            $crate::Tentative::commit(__tentative);
+            $body
+        } else $($else)+)
+    }};
+    {@try_expr [$($e:tt)*] ($($iter:tt)+) [$($g:tt)*]} => {{
+        use $crate::Rewind as _;
+        let mut __tentative = ($($iter)+).tentative();
+        let __result = $crate::iunpack!(@tail(top) [$($e)*] &mut __tentative $($g)*);
+        if __result.is_ok() {
+            $crate::Tentative::commit(__tentative);
+        }
+        __result
+    }};
+    {@tail(let) [$($e:tt)*] $($rest:tt)+} => {
+        $crate::ilet!(@iter[$($e)*][] $($rest)+)
+    };
+    {@tail(assert $src:tt) [$($e:tt)*] $($rest:tt)+} => {
+        $crate::iassert_unpack!(@iter $src [$($e)*][] $($rest)+)
+    };
+    {@done(sub $cfg:tt [$($e:tt)*] $($rest:tt)*) [$($sub:tt)*]} => {
+        $crate::iunpack!(@sep $cfg [$($e)*] ([$($sub)*]) $($rest)*)
+    };
+    {@done(imatch $($k:tt)*) [([$($e:tt)*])]} => {
+        $crate::imatch!($($k)* [$($e)*])
+    };
+    {@tail(sub $($t:tt)*) $($rest:tt)*} => {
+        ::core::compile_error!("unexpected `=` in nested pattern")
+    };
+    {@done $($t:tt)*} => {
+        ::core::compile_error!("expected `=` after patterns")
+    };
+    // take the tokens until a delimiter at the top level, four tokens at a time
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*]} => {
+        $crate::$m!($($k)* [$($acc)*])
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] , $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)*] , $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] = $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)*] = $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] => $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)*] => $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] if $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)*] if $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] else $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)*] else $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt , $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a] , $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt = $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a] = $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt => $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a] => $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt if $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a] if $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt else $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a] else $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt , $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b] , $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt = $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b] = $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt => $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b] => $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt if $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b] if $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt else $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b] else $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt $c:tt , $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b $c] , $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt $c:tt = $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b $c] = $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt $c:tt => $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b $c] => $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt $c:tt if $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b $c] if $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt $c:tt else $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b $c] else $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt $c:tt $d:tt , $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b $c $d] , $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt $c:tt $d:tt = $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b $c $d] = $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt $c:tt $d:tt => $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b $c $d] => $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt $c:tt $d:tt if $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b $c $d] if $($rest)*)
+    };
+    {@scan($m:ident $($k:tt)*) [$($acc:tt)*] $a:tt $b:tt $c:tt $d:tt else $($rest:tt)*} => {
+        $crate::$m!($($k)* [$($acc)* $a $b $c $d] else $($rest)*)
+    };
+    {@scan $k:tt [$($acc:tt)*] $a:tt $b:tt $c:tt $d:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan $k [$($acc)* $a $b $c $d] $($rest)*)
+    };
+    {@scan $k:tt [$($acc:tt)*] $a:tt $($rest:tt)*} => {
+        $crate::iunpack!(@scan $k [$($acc)* $a] $($rest)*)
+    };
+    // the tuple of bindings, `@tuple(mut)` builds the `let` pattern of it
+    {@tuple$m:tt[$($acc:tt)*] ($a:ident) ($b:ident) ($c:ident) ($d:ident) $($e:tt)*} => {
+        $crate::iunpack!(@tuple$m[$($acc)* $a, $b, $c, $d,] $($e)*)
+    };
+    {@tuple$m:tt[$($acc:tt)*] (if $elem:tt $guard:tt) $($e:tt)*} => {
+        $crate::iunpack!(@tuple$m[$($acc)*] $elem $($e)*)
+    };
+    {@tuple$m:tt[$($acc:tt)*] (while $star:tt $pred:tt) $($e:tt)*} => {
+        $crate::iunpack!(@tuple$m[$($acc)*] $star $($e)*)
+    };
+    {@tuple$m:tt[$($acc:tt)*] (? $elem:tt) $($e:tt)*} => {
+        $crate::iunpack!(@tuple$m[$($acc)*] $elem $($e)*)
+    };
+    {@tuple$m:tt[$($acc:tt)*] (= $elem:tt $default:tt) $($e:tt)*} => {
+        $crate::iunpack!(@tuple$m[$($acc)*] $elem $($e)*)
+    };
+    {@tuple($($m:tt)?)[$($acc:tt)*] (mut $name:ident) $($e:tt)*} => {
+        $crate::iunpack!(@tuple($($m)?)[$($acc)* $($m)? $name,] $($e)*)
+    };
+    {@tuple$m:tt[$($acc:tt)*] ($name:ident) $($e:tt)*} => {
+        $crate::iunpack!(@tuple$m[$($acc)* $name,] $($e)*)
+    };
+    {@tuple$m:tt[$($acc:tt)*] ([$($sub:tt)*]) $($e:tt)*} => {
+        $crate::iunpack!(@tuple$m[$($acc)* $crate::iunpack!(@tuple$m[] $($sub)*),] $($e)*)
+    };
+    {@tuple($($m:tt)?)[$($acc:tt)*] (*= $mid:ident) $($e:tt)*} => {
+        $crate::iunpack!(@tuple($($m)?)[$($acc)* $($m)? $mid,] $($e)*)
+    };
+    {@tuple$m:tt[$($acc:tt)*] (* $(*)? $mid:ident $($t:tt)*) $($e:tt)*} => {
+        $crate::iunpack!(@tuple$m[$($acc)* $mid,] $($e)*)
+    };
+    {@tuple$m:tt[$($acc:tt)*] $skip:tt $($e:tt)*} => {
+        $crate::iunpack!(@tuple$m[$($acc)*] $($e)*)
+    };
+    {@tuple$m:tt[$($acc:tt)*]} => {
+        ($($acc)*)
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)*]
+        (* $($l:tt)*) ($sep:literal) (* $($r:tt)*) $($b:tt)*
+    } => {
+        $crate::iunpack!(@emit $data [$($f)*] [$($o)*]
+            (split (* $($l)*) ($sep) (* $($r)*)) [$($b)*])
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)*]
+        (* $($l:tt)*) (== $x:tt) (* $($r:tt)*) $($b:tt)*
+    } => {
+        $crate::iunpack!(@emit $data [$($f)*] [$($o)*]
+            (split (* $($l)*) (== $x) (* $($r)*)) [$($b)*])
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)*]
+        (* $($l:tt)*) (last $sep:tt) (* $($r:tt)*) $($b:tt)*
+    } => {
+        $crate::iunpack!(@emit $data [$($f)*] [$($o)*]
+            (split (* $($l)*) (last $sep) (* $($r)*)) [$($b)*])
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)*] (* $($star:tt)*) $($b:tt)*} => {
+        $crate::iunpack!(@emit $data [$($f)*] [$($o)*] (* $($star)*) [$($b)*])
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)*] (*= $($star:tt)*) $($b:tt)*} => {
+        $crate::iunpack!(@emit $data [$($f)*] [$($o)*] (*= $($star)*) [$($b)*])
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)*] (? $($t:tt)*) $($rest:tt)*} => {
+        $crate::iunpack!(@split $data [$($f)*] [$($o)* (? $($t)*)] $($rest)*)
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)*] (= $($t:tt)*) $($rest:tt)*} => {
+        $crate::iunpack!(@split $data [$($f)*] [$($o)* (= $($t)*)] $($rest)*)
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)*] (if (? $($t:tt)*) $g:tt) $($rest:tt)*} => {
+        $crate::iunpack!(@split $data [$($f)*] [$($o)* (if (? $($t)*) $g)] $($rest)*)
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)*] (if (= $($t:tt)*) $g:tt) $($rest:tt)*} => {
+        $crate::iunpack!(@split $data [$($f)*] [$($o)* (if (= $($t)*) $g)] $($rest)*)
+    };
+    {@split $data:tt [$($f:tt)*] [] $a:tt (* $($l:tt)*) $sep:tt (* $($r:tt)*) $($b:tt)*} => {
+        $crate::iunpack!(@split $data [$($f)* $a] [] (* $($l)*) $sep (* $($r)*) $($b)*)
+    };
+    {@split $data:tt [$($f:tt)*] [] $a:tt (* $($star:tt)*) $($b:tt)*} => {
+        $crate::iunpack!(@emit $data [$($f)* $a] [] (* $($star)*) [$($b)*])
+    };
+    {@split $data:tt [$($f:tt)*] [] $a:tt ($b:tt) (* $($star:tt)*)} => {
+        $crate::iunpack!(@emit $data [$($f)* $a ($b)] [] (* $($star)*) [])
+    };
+    {@split $data:tt [$($f:tt)*] [] $a:tt (*) $($rest:tt)*} => {
+        $crate::iunpack!(@split $data [$($f)* $a] [] (*) $($rest)*)
+    };
+    {@split $data:tt [$($f:tt)*] [] $a:tt ($b:tt) (*) $($rest:tt)*} => {
+        $crate::iunpack!(@split $data [$($f)* $a ($b)] [] (*) $($rest)*)
+    };
+    {@split $data:tt [$($f:tt)*] [] $a:tt ($b:tt) ($c:tt) (*) $($rest:tt)*} => {
+        $crate::iunpack!(@split $data [$($f)* $a ($b) ($c)] [] (*) $($rest)*)
+    };
+    {@split $data:tt [$($f:tt)*] [] $a:tt ($b:tt) ($c:tt) ($d:tt) $($rest:tt)*} => {
+        $crate::iunpack!(@split $data [$($f)* $a ($b) ($c) ($d)] [] $($rest)*)
+    };
+    {@split $data:tt [$($f:tt)*] [] $a:tt ($b:tt) ($c:tt)} => {
+        $crate::iunpack!(@emit $data [$($f)* $a ($b) ($c)] [] () [])
+    };
+    {@split $data:tt [$($f:tt)*] [] $a:tt ($b:tt)} => {
+        $crate::iunpack!(@emit $data [$($f)* $a ($b)] [] () [])
+    };
+    {@split $data:tt [$($f:tt)*] [] $a:tt} => {
+        $crate::iunpack!(@emit $data [$($f)* $a] [] () [])
+    };
+    {@split $data:tt [$($f:tt)*] [] $e:tt $($rest:tt)*} => {
+        $crate::iunpack!(@split $data [$($f)* $e] [] $($rest)*)
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)+] $($rest:tt)+} => {
+        ::core::compile_error!("required pattern cannot follow optional pattern")
+    };
+    {@split $data:tt [$($f:tt)*] [$($o:tt)*]} => {
+        $crate::iunpack!(@emit $data [$($f)*] [$($o)*] () [])
+    };
+
+    // generate code
+    {@elem($value:ident, $index:expr, $from_back:expr, $body:block, $else:tt)
+        (if $elem:tt [$guard:expr])
+    } => {
+        $crate::iunpack!(@elem($value, $index, $from_back, {
+            if $guard {
+                $body
+            } else {
+                $crate::iunpack!(@fail($crate::UnpackError::Guard {
+                    index: $index,
+                    from_back: $from_back,
+                }) $else)
+            }
+        }, $else) $elem)
+    };
+    {@elem($value:ident, $index:expr, $from_back:expr, $body:block, $else:tt)
+        (== [$x:expr])
+    } => {
+        if $value == $x {
+            $body
+        } else {
+            $crate::iunpack!(@fail($crate::UnpackError::Mismatch {
+                index: $index,
+                from_back: $from_back,
+            }) $else)
+        }
+    };
+    {@elem($value:ident, $index:expr, $from_back:expr, $body:block, $else:tt)
+        ([$($sub:tt)*])
+    } => {
+        $crate::iunpack!(@split(unpack($value), $body, [
+            @nest($index, $from_back) $else
+        ]) [] [] $($sub)*)
+    };
+    {@elem($value:ident, $index:expr, $from_back:expr, $body:block, $else:tt)
+        (while $($t:tt)*)
+    } => {
+        ::core::compile_error!("take-while star pattern must be before the star pattern")
+    };
+    {@elem($value:ident, $index:expr, $from_back:expr, $body:block, $else:tt)
+        (? $($t:tt)*)
+    } => {
+        ::core::compile_error!("optional pattern must be before the star pattern")
+    };
+    {@elem($value:ident, $index:expr, $from_back:expr, $body:block, $else:tt)
+        (= $($t:tt)*)
+    } => {
+        ::core::compile_error!("optional pattern must be before the star pattern")
+    };
+    {@elem($value:ident, $index:expr, $from_back:expr, $body:block, $else:tt)
+        ($pat:pat)
+    } => {{
+        #[allow(unreachable_patterns)]
+        match $value {
+            $pat => $body,
+            _ => $crate::iunpack!(@fail($crate::UnpackError::Mismatch {
+                index: $index,
+                from_back: $from_back,
+            }) $else),
+        }
+    }};
+    {@revpat_do_iter_back($iter:ident, $body:block, $else:tt, $front:expr, $len:expr)
+    } => ($body);
+    {@revpat_do_iter_back($iter:ident, $body:block, $else:tt, $front:expr, $len:expr)
+        @elem $used:tt $($elem:tt)*
+    } => {
+        $crate::iunpack!(@revpat_do_iter_back($iter, {
+            match ::core::iter::DoubleEndedIterator::next_back(&mut $iter) {
+                ::core::option::Option::Some(__elem) => {
+                    $crate::iunpack!(@elem(
+                        __elem,
+                        $crate::iunpack!(@count $($elem)*),
+                        true,
+                        $body,
+                        $else
+                    ) $used)
+                },
+                ::core::option::Option::None => {
+                    $crate::iunpack!(@fail($crate::UnpackError::TooFew {
+                        got: $front + $crate::iunpack!(@count $($elem)*),
+                        expected: $len,
+                    }) $else)
+                },
+            }
+        }, $else, $front, $len) $($elem)*)
+    };
+    // match the last single token patterns inline, except the sub patterns
+    {@revpat_do_iter_back($iter:ident, $body:block, $else:tt, $front:expr, $len:expr)
+        ([$($sub:tt)*]) $($elem:tt)*
+    } => {
+        $crate::iunpack!(@revpat_do_iter_back($iter, $body, $else, $front, $len)
+            @elem ([$($sub)*]) $($elem)*)
+    };
+    {@revpat_do_iter_back($iter:ident, $body:block, $else:tt, $front:expr, $len:expr)
+        ($a:tt) ([$($sub:tt)*]) $($elem:tt)*
+    } => {
+        $crate::iunpack!(@revpat_do_iter_back($iter, $body, $else, $front, $len)
+            @elem ($a) ([$($sub)*]) $($elem)*)
+    };
+    {@revpat_do_iter_back($iter:ident, $body:block, $else:tt, $front:expr, $len:expr)
+        ($a:tt) ($b:tt)
+    } => {{
+        #[allow(unreachable_patterns)]
+        match ::core::iter::DoubleEndedIterator::next_back(&mut $iter) {
+            ::core::option::Option::Some($b) => {
+                match ::core::iter::DoubleEndedIterator::next_back(&mut $iter) {
+                    ::core::option::Option::Some($a) => $body,
+                    __elem => $crate::iunpack!(@miss(__elem, 1, $front + 1, $len, $else, true)),
+                }
+            },
+            __elem => $crate::iunpack!(@miss(__elem, 0, $front, $len, $else, true)),
+        }
+    }};
+    {@revpat_do_iter_back($iter:ident, $body:block, $else:tt, $front:expr, $len:expr)
+        ($a:tt)
+    } => {{
+        #[allow(unreachable_patterns)]
+        match ::core::iter::DoubleEndedIterator::next_back(&mut $iter) {
+            ::core::option::Option::Some($a) => $body,
+            __elem => $crate::iunpack!(@miss(__elem, 0, $front, $len, $else, true)),
+        }
+    }};
+    {@revpat_do_iter_back($iter:ident, $body:block, $else:tt, $front:expr, $len:expr)
+        $used:tt $($elem:tt)*
+    } => {
+        $crate::iunpack!(@revpat_do_iter_back($iter, $body, $else, $front, $len)
+            @elem $used $($elem)*)
+    };
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        (while (* $(&mut $place:tt)? $($mid:ident $(: $ty:ty)?)?) [$pred:expr]) $($elem:tt)*
+    } => {{
+        let mut __rest = ::core::option::Option::None;
+        let __mid = $crate::__private::TakeWhile::new(&mut $iter, &mut __rest, $pred);
+        $crate::iunpack!(@collect_into(__mid, {
+            #[allow(unused_mut)]
+            let mut $iter = ::core::iter::Iterator::chain(
+                ::core::iter::IntoIterator::into_iter(__rest),
+                $iter,
+            );
+            $crate::iunpack!(@sized_pat(
+                $iter,
+                $front,
+                $body,
+                $else,
+                $idx + 1,
+                $got,
+                $len
+            ) $($elem)*)
+        }, $else) $(&mut $place)? $($mid $(: $ty)?)?)
+    }};
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        @elem $used:tt $($elem:tt)*
+    } => {
+        match ::core::iter::Iterator::next(&mut $iter) {
+            ::core::option::Option::Some(__elem) => {
+                $crate::iunpack!(@elem(__elem, $idx, false, {
+                    $crate::iunpack!(@sized_pat(
+                        $iter,
+                        $front,
+                        $body,
+                        $else,
+                        $idx + 1,
+                        $got + 1,
+                        $len
+                    ) $($elem)*)
+                }, $else) $used)
+            },
+            ::core::option::Option::None => {
+                $crate::iunpack!(@fail($crate::UnpackError::TooFew {
+                    got: $got,
+                    expected: $len,
+                }) $else)
+            },
+        }
+    };
+    // match the patterns inline, sub patterns are not parsed as slice patterns
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        (if ([$($sub:tt)*]) $guard:tt) $($elem:tt)*
+    } => {
+        $crate::iunpack!(@sized_pat($iter, $front, $body, $else, $idx, $got, $len)
+            @elem (if ([$($sub)*]) $guard) $($elem)*)
+    };
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        ([$($sub:tt)*]) $($elem:tt)*
+    } => {
+        $crate::iunpack!(@sized_pat($iter, $front, $body, $else, $idx, $got, $len)
+            @elem ([$($sub)*]) $($elem)*)
+    };
+    // match the single token patterns four at a time inline, except the sub patterns
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        ($a:tt) ([$($sub:tt)*]) $($elem:tt)*
+    } => {
+        $crate::iunpack!(@sized_pat($iter, $front, $body, $else, $idx, $got, $len)
+            @elem ($a) ([$($sub)*]) $($elem)*)
+    };
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        ($a:tt) ($b:tt) ([$($sub:tt)*]) $($elem:tt)*
+    } => {
+        $crate::iunpack!(@sized_pat($iter, $front, $body, $else, $idx, $got, $len)
+            @elem ($a) ($b) ([$($sub)*]) $($elem)*)
     };
-    {@sized_pat($iter:ident, $body:block, $errbody:block, $errval:ident)
-        $($fpat:pat $(, $pat:pat)*)?
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        ($a:tt) ($b:tt) ($c:tt) ([$($sub:tt)*]) $($elem:tt)*
     } => {
-        $crate::iunpack!(@if$((
-            if let ::core::option::Option::Some($fpat)
-            = ::core::iter::Iterator::next(&mut $iter) {
-                $errval += 1;
+        $crate::iunpack!(@sized_pat($iter, $front, $body, $else, $idx, $got, $len)
+            @elem ($a) ($b) ($c) ([$($sub)*]) $($elem)*)
+    };
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        ($a:tt) ($b:tt) ($c:tt) ($d:tt) $($elem:tt)*
+    } => {{
+        #[allow(unreachable_patterns)]
+        match ::core::iter::Iterator::next(&mut $iter) {
+            ::core::option::Option::Some($a) => match ::core::iter::Iterator::next(&mut $iter) {
+                ::core::option::Option::Some($b) => match ::core::iter::Iterator::next(&mut $iter) {
+                    ::core::option::Option::Some($c) => match ::core::iter::Iterator::next(&mut $iter) {
+                        ::core::option::Option::Some($d) => $crate::iunpack!(@sized_pat(
+                            $iter,
+                            $front,
+                            $body,
+                            $else,
+                            $idx + 4,
+                            $got + 4,
+                            $len
+                        ) $($elem)*),
+                        __elem => $crate::iunpack!(@miss(__elem, $idx + 3, $got + 3, $len, $else)),
+                    },
+                    __elem => $crate::iunpack!(@miss(__elem, $idx + 2, $got + 2, $len, $else)),
+                },
+                __elem => $crate::iunpack!(@miss(__elem, $idx + 1, $got + 1, $len, $else)),
+            },
+            __elem => $crate::iunpack!(@miss(__elem, $idx, $got, $len, $else)),
+        }
+    }};
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        ($a:tt) ($b:tt) ($c:tt)
+    } => {{
+        #[allow(unreachable_patterns)]
+        match ::core::iter::Iterator::next(&mut $iter) {
+            ::core::option::Option::Some($a) => match ::core::iter::Iterator::next(&mut $iter) {
+                ::core::option::Option::Some($b) => match ::core::iter::Iterator::next(&mut $iter) {
+                    ::core::option::Option::Some($c) => {
+                        let $front = $got + 3;
+                        $body
+                    },
+                    __elem => $crate::iunpack!(@miss(__elem, $idx + 2, $got + 2, $len, $else)),
+                },
+                __elem => $crate::iunpack!(@miss(__elem, $idx + 1, $got + 1, $len, $else)),
+            },
+            __elem => $crate::iunpack!(@miss(__elem, $idx, $got, $len, $else)),
+        }
+    }};
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        ($a:tt) ($b:tt)
+    } => {{
+        #[allow(unreachable_patterns)]
+        match ::core::iter::Iterator::next(&mut $iter) {
+            ::core::option::Option::Some($a) => match ::core::iter::Iterator::next(&mut $iter) {
+                ::core::option::Option::Some($b) => {
+                    let $front = $got + 2;
+                    $body
+                },
+                __elem => $crate::iunpack!(@miss(__elem, $idx + 1, $got + 1, $len, $else)),
+            },
+            __elem => $crate::iunpack!(@miss(__elem, $idx, $got, $len, $else)),
+        }
+    }};
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        ($a:tt)
+    } => {{
+        #[allow(unreachable_patterns)]
+        match ::core::iter::Iterator::next(&mut $iter) {
+            ::core::option::Option::Some($a) => {
+                let $front = $got + 1;
+                $body
+            },
+            __elem => $crate::iunpack!(@miss(__elem, $idx, $got, $len, $else)),
+        }
+    }};
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        (if ($pat:pat) [$guard:expr]) $($elem:tt)*
+    } => {{
+        #[allow(unreachable_patterns)]
+        match ::core::iter::Iterator::next(&mut $iter) {
+            ::core::option::Option::Some($pat) => if $guard {
+                $crate::iunpack!(@sized_pat(
+                    $iter,
+                    $front,
+                    $body,
+                    $else,
+                    $idx + 1,
+                    $got + 1,
+                    $len
+                ) $($elem)*)
+            } else {
+                $crate::iunpack!(@fail($crate::UnpackError::Guard {
+                    index: $idx,
+                    from_back: false,
+                }) $else)
+            },
+            ::core::option::Option::Some(_) => {
+                $crate::iunpack!(@fail($crate::UnpackError::Mismatch {
+                    index: $idx,
+                    from_back: false,
+                }) $else)
+            },
+            ::core::option::Option::None => {
+                $crate::iunpack!(@fail($crate::UnpackError::TooFew {
+                    got: $got,
+                    expected: $len,
+                }) $else)
+            },
+        }
+    }};
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        (if $($t:tt)*) $($elem:tt)*
+    } => {
+        $crate::iunpack!(@sized_pat($iter, $front, $body, $else, $idx, $got, $len)
+            @elem (if $($t)*) $($elem)*)
+    };
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        ($pat:pat) $($elem:tt)*
+    } => {{
+        #[allow(unreachable_patterns)]
+        match ::core::iter::Iterator::next(&mut $iter) {
+            ::core::option::Option::Some($pat) => {
                 $crate::iunpack!(@sized_pat(
                     $iter,
+                    $front,
                     $body,
-                    $errbody,
-                    $errval
-                ) $($pat),*)
-            } else $errbody
-        ))? else $body)
-    };
-    {@sized_pat($iter:ident, $body:block, $errbody:block)
-        $($fpat:pat $(, $pat:pat)*)?
+                    $else,
+                    $idx + 1,
+                    $got + 1,
+                    $len
+                ) $($elem)*)
+            },
+            ::core::option::Option::Some(_) => {
+                $crate::iunpack!(@fail($crate::UnpackError::Mismatch {
+                    index: $idx,
+                    from_back: false,
+                }) $else)
+            },
+            ::core::option::Option::None => {
+                $crate::iunpack!(@fail($crate::UnpackError::TooFew {
+                    got: $got,
+                    expected: $len,
+                }) $else)
+            },
+        }
+    }};
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        (== [$x:expr]) $($elem:tt)*
     } => {
-        $crate::iunpack!(@if$((
-            if let ::core::option::Option::Some($fpat)
-            = ::core::iter::Iterator::next(&mut $iter) {
+        match ::core::iter::Iterator::next(&mut $iter) {
+            ::core::option::Option::Some(__elem) => if __elem == $x {
                 $crate::iunpack!(@sized_pat(
                     $iter,
+                    $front,
                     $body,
-                    $errbody
-                ) $($pat),*)
-            } else $errbody
-        ))? else $body)
+                    $else,
+                    $idx + 1,
+                    $got + 1,
+                    $len
+                ) $($elem)*)
+            } else {
+                $crate::iunpack!(@fail($crate::UnpackError::Mismatch {
+                    index: $idx,
+                    from_back: false,
+                }) $else)
+            },
+            ::core::option::Option::None => {
+                $crate::iunpack!(@fail($crate::UnpackError::TooFew {
+                    got: $got,
+                    expected: $len,
+                }) $else)
+            },
+        }
     };
-    // unused err value
-    {
-        $($pat:pat),* $(,)?
-        = $iter:expr => $body:block
-        else $errbody:expr
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+        $used:tt $($elem:tt)*
+    } => {
+        $crate::iunpack!(@sized_pat($iter, $front, $body, $else, $idx, $got, $len)
+            @elem $used $($elem)*)
+    };
+    {@sized_pat($iter:ident, $front:ident, $body:block, $else:tt, $idx:expr, $got:expr, $len:expr)
+    } => {{
+        let $front = $got;
+        $body
+    }};
+    // the next element is mismatched or missing, from the front by default
+    {@miss($elem:ident, $idx:expr, $got:expr, $len:expr, $else:tt $(, $from_back:expr)?)} => {
+        match $elem {
+            ::core::option::Option::Some(_) => {
+                $crate::iunpack!(@fail($crate::UnpackError::Mismatch {
+                    index: $idx,
+                    from_back: $crate::iunpack!(@if$(($from_back))? else false),
+                }) $else)
+            },
+            ::core::option::Option::None => {
+                $crate::iunpack!(@fail($crate::UnpackError::TooFew {
+                    got: $got,
+                    expected: $len,
+                }) $else)
+            },
+        }
+    };
+    {@opt_pat($next:expr, $body:block, $else:tt, $idx:expr) @elem $used:tt $($elem:tt)*} => {
+        $crate::iunpack!(@opt_elem($next, $idx, {
+            $crate::iunpack!(@opt_pat($next, $body, $else, $idx + 1) $($elem)*)
+        }, $else) $used)
+    };
+    {@opt_pat($next:expr, $body:block, $else:tt, $idx:expr)
+        (? ($($name:tt)+)) $($elem:tt)*
+    } => {{
+        let $($name)+ = $next;
+        $crate::iunpack!(@opt_pat($next, $body, $else, $idx + 1) $($elem)*)
+    }};
+    {@opt_pat($next:expr, $body:block, $else:tt, $idx:expr)
+        (= ([$($sub:tt)*]) $default:tt) $($elem:tt)*
+    } => {
+        $crate::iunpack!(@opt_pat($next, $body, $else, $idx)
+            @elem (= ([$($sub)*]) $default) $($elem)*)
+    };
+    {@opt_pat($next:expr, $body:block, $else:tt, $idx:expr)
+        (= ($pat:pat) [$default:expr]) $($elem:tt)*
+    } => {{
+        #[allow(unreachable_patterns)]
+        match match $next {
+            ::core::option::Option::Some(__elem) => __elem,
+            ::core::option::Option::None => $default,
+        } {
+            $pat => $crate::iunpack!(@opt_pat($next, $body, $else, $idx + 1) $($elem)*),
+            _ => $crate::iunpack!(@fail($crate::UnpackError::Mismatch {
+                index: $idx,
+                from_back: false,
+            }) $else),
+        }
+    }};
+    {@opt_pat($next:expr, $body:block, $else:tt, $idx:expr) $used:tt $($elem:tt)*} => {
+        $crate::iunpack!(@opt_pat($next, $body, $else, $idx) @elem $used $($elem)*)
+    };
+    {@opt_pat($next:expr, $body:block, $else:tt, $idx:expr)} => ($body);
+    {@opt_elem($next:expr, $idx:expr, $body:block, $else:tt)
+        (if $elem:tt [$guard:expr])
+    } => {
+        $crate::iunpack!(@opt_elem($next, $idx, {
+            if $guard {
+                $body
+            } else {
+                $crate::iunpack!(@fail($crate::UnpackError::Guard {
+                    index: $idx,
+                    from_back: false,
+                }) $else)
+            }
+        }, $else) $elem)
+    };
+    {@opt_elem($next:expr, $idx:expr, $body:block, $else:tt)
+        (? ($($name:tt)+))
+    } => {{
+        let $($name)+ = $next;
+        $body
+    }};
+    {@opt_elem($next:expr, $idx:expr, $body:block, $else:tt)
+        (= $elem:tt [$default:expr])
     } => {{
+        let __elem = match $next {
+            ::core::option::Option::Some(__elem) => __elem,
+            ::core::option::Option::None => $default,
+        };
+        $crate::iunpack!(@elem(__elem, $idx, false, $body, $else) $elem)
+    }};
+    // without optional and star patterns
+    {@emit(unpack($iter:expr), $body:block, $else:tt) [$($f:tt)*] [] () []} => {{
         let mut __iter = ::core::iter::IntoIterator::into_iter($iter);
-        $crate::iunpack!(@sized_pat(__iter, {
-            if let ::core::option::Option::Some(_)
-            = ::core::iter::Iterator::next(&mut __iter) {
-                $errbody
+        $crate::iunpack!(@sized_pat(__iter, __front, {
+            if let ::core::option::Option::Some(_) = ::core::iter::Iterator::next(&mut __iter) {
+                $crate::iunpack!(@fail($crate::UnpackError::TooMany {
+                    expected: $crate::iunpack!(@count_req $($f)*),
+                }) $else)
             } else {
                 $body
             }
-        }, { $errbody }) $($pat),*)
+        }, $else, 0, 0, $crate::iunpack!(@count_req $($f)*)) $($f)*)
     }};
-    // used err value
-    {
-        $($pat:pat),* $(,)?
-        = $iter:expr => $body:block
-        else($err:ident) $errbody:block
+    {@emit(unpack($iter:expr), $body:block, $else:tt) [$($f:tt)*] [$($o:tt)*] () []} => {{
+        let mut __iter = ::core::iter::IntoIterator::into_iter($iter);
+        $crate::iunpack!(@sized_pat(__iter, __front, {
+            $crate::iunpack!(@opt_pat(::core::iter::Iterator::next(&mut __iter), {
+                if let ::core::option::Option::Some(_)
+                = ::core::iter::Iterator::next(&mut __iter) {
+                    $crate::iunpack!(@fail($crate::UnpackError::TooMany {
+                        expected: $crate::iunpack!(@count_req $($f)* $($o)*),
+                    }) $else)
+                } else {
+                    $body
+                }
+            }, $else, $crate::iunpack!(@count $($f)*)) $($o)*)
+        }, $else, 0, 0, $crate::iunpack!(@count_req $($f)*)) $($f)*)
+    }};
+    // use DoubleEndedIterator if implemented, otherwise delay elements like `**`
+    {@emit(unpack($iter:expr), $body:block, $else:tt $(, $forward:ident)?)
+        [$($f:tt)*] [$($o:tt)*]
+        (* $(&mut $place:tt)? $($mid:ident $(: $ty:ty)?)? $({$($bound:tt)*})?) [$($b:tt)+]
     } => {{
         let mut __iter = ::core::iter::IntoIterator::into_iter($iter);
-        let mut $err = 0usize;
-        $crate::iunpack!(@sized_pat(__iter, {
-            if let ::core::option::Option::Some(_)
-            = ::core::iter::Iterator::next(&mut __iter) {
-                $errbody
+        let __next_back = $crate::iunpack!(@next_back(__iter $(, $forward)?));
+        $crate::iunpack!(@sized_pat(__iter, __front, {
+            let mut __buf = [$(
+                $crate::iunpack!(@if(::core::option::Option::None) else $o),
+            )* $(
+                $crate::iunpack!(@if(::core::option::Option::None) else $b)
+            ),+];
+            let __bounds = $crate::__private::StarBounds::new(
+                $crate::iunpack!(@if$(($($bound)*))? else ..));
+            let mut __ends = $crate::__private::Ends::new(
+                &mut __iter,
+                __next_back,
+                &mut __buf,
+                $crate::iunpack!(@count $($o)*),
+                __bounds.limit(),
+            );
+            // the delayed middle elements are taken before the end elements
+            let __mid = if __ends.is_delayed() {
+                ::core::option::Option::Some($crate::iunpack!(@ends_collect(__ends, __bounds)
+                    $(&mut $place)? $($mid $(: $ty)?)?))
             } else {
-                $body
+                ::core::option::Option::None
+            };
+            if let ::core::option::Option::Some(::core::result::Result::Err(__err)) = __mid {
+                $crate::iunpack!(@fail(__err) $else)
+            } else {
+                $crate::iunpack!(@revpat_do_iter_back(__ends, {
+                    $crate::iunpack!(@opt_pat(::core::iter::Iterator::next(&mut __ends), {
+                        match match __mid {
+                            ::core::option::Option::Some(__mid) => __mid,
+                            ::core::option::Option::None => $crate::iunpack!(
+                                @ends_mid(__ends, __bounds)
+                                [$(&mut $place)? $($mid $(: $ty)?)?] $({$($bound)*})?
+                            ),
+                        } {
+                            ::core::result::Result::Ok(__star) => {
+                                $(let $mid = __star;)?
+                                $body
+                            },
+                            ::core::result::Result::Err(__err) => {
+                                $crate::iunpack!(@fail(__err) $else)
+                            },
+                        }
+                    }, $else, $crate::iunpack!(@count $($f)*)) $($o)*)
+                }, $else, __front, $crate::iunpack!(@count_req $($f)* $($b)*)) $($b)+)
             }
-        }, { $errbody }, $err) $($pat),*)
+        }, $else, 0, 0, $crate::iunpack!(@count_req $($f)* $($b)*)) $($f)*)
     }};
-    // use DoubleEndedIterator
-    {
-        $($fpat:pat ,)* * $($mid:ident $(: $ty:ty)?)? $(, $bpat:pat)* $(,)?
-        = $iter:expr => $body:block
-        else $errbody:expr
+    // without end patterns, take the middle elements from the front
+    {@emit(unpack($iter:expr), $body:block, $else:tt)
+        [$($f:tt)*] [$($o:tt)*]
+        (* $(&mut $place:tt)? $($mid:ident $(: $ty:ty)?)? $({$($bound:tt)*})?) []
+    } => {{
+        let mut __iter = ::core::iter::IntoIterator::into_iter($iter);
+        $crate::iunpack!(@sized_pat(__iter, __front, {
+            $crate::iunpack!(@opt_pat(::core::iter::Iterator::next(&mut __iter), {
+                $crate::iunpack!(@collect(__iter, $body, $else)
+                    $(&mut $place)? $($mid $(: $ty)?)? $({$($bound)*})?)
+            }, $else, $crate::iunpack!(@count $($f)*)) $($o)*)
+        }, $else, 0, 0, $crate::iunpack!(@count_req $($f)*)) $($f)*)
+    }};
+    {@next_back($iter:ident, forward)} => (::core::option::Option::None);
+    {@next_back($iter:ident)} => {{
+        #[allow(unused_imports)]
+        use $crate::__private::{ViaDoubleEnded as _, ViaForward as _};
+        (&$crate::__private::Probe::of(&$iter)).next_back_fn()
+    }};
+    {@collect($iter:ident, $body:block, $else:tt)} => ($body);
+    {@collect($iter:ident, $body:block, $else:tt)
+        $(&mut $place:tt)? $($mid:ident $(: $ty:ty)?)? {$($bound:tt)*}
+    } => {{
+        let __bounds = $crate::__private::StarBounds::new($($bound)*);
+        let __mid = ::core::iter::Iterator::take(
+            ::core::iter::Iterator::by_ref(&mut $iter),
+            __bounds.limit(),
+        );
+        let mut __got = 0;
+        let __mid = ::core::iter::Iterator::inspect(__mid, |_| __got += 1);
+        $crate::iunpack!(@collect_into(__mid, {
+            match __bounds.check(__got) {
+                ::core::result::Result::Ok(()) => $body,
+                ::core::result::Result::Err(__err) => {
+                    $crate::iunpack!(@fail(__err) $else)
+                },
+            }
+        }, $else) $(&mut $place)? $($mid $(: $ty)?)?)
+    }};
+    {@collect($iter:ident, $body:block, $else:tt) $(&mut $place:tt)? $($mid:ident $(: $ty:ty)?)?} => {
+        match $crate::iunpack!(@collect_result($iter) $(&mut $place)? $($mid $(: $ty)?)?) {
+            ::core::result::Result::Ok(__star) => {
+                $(let $mid = __star;)?
+                $body
+            },
+            ::core::result::Result::Err(__err) => {
+                $crate::iunpack!(@fail(__err) $else)
+            },
+        }
+    };
+    // collect all elements into star pattern, or drop them
+    {@collect_into($iter:expr, $body:block, $else:tt)} => {{
+        ::core::iter::Iterator::for_each($iter, ::core::mem::drop);
+        $body
+    }};
+    {@collect_into($iter:expr, $body:block, $else:tt) $mid:ident $(: $ty:ty)?} => {
+        match $crate::iunpack!(@collect_result($iter) $mid $(: $ty)?) {
+            ::core::result::Result::Ok($mid) => $body,
+            ::core::result::Result::Err(__err) => {
+                $crate::iunpack!(@fail(__err) $else)
+            },
+        }
+    };
+    {@collect_into($iter:expr, $body:block, $else:tt) &mut $place:tt} => {
+        match $crate::iunpack!(@collect_result($iter) &mut $place) {
+            ::core::result::Result::Ok(()) => $body,
+            ::core::result::Result::Err(__err) => {
+                $crate::iunpack!(@fail(__err) $else)
+            },
+        }
+    };
+    // the result of collecting the elements into star pattern
+    {@collect_result($iter:expr)} => {{
+        ::core::iter::Iterator::for_each($iter, ::core::mem::drop);
+        ::core::result::Result::<(), $crate::UnpackError>::Ok(())
+    }};
+    {@collect_result($iter:expr) $mid:ident $(: $ty:ty)?} => {
+        $crate::iunpack!(@collector_call(
+            $crate::iunpack!(@collector $($ty)?),
+            collect($iter)
+        ))
+    };
+    {@collect_result($iter:expr) &mut [$($place:tt)+]} => {{
+        let __coll = &mut $($place)+;
+        __coll.clear();
+        $crate::iunpack!(@collector_call(
+            $crate::__private::Collector::of(&*__coll),
+            extend(__coll, $iter)
+        ))
+    }};
+    {@collector $($ty:ty)?} => {
+        $crate::__private::Collector::<
+            $crate::iunpack!(@if$(($ty))? else $crate::__private::Vec<_>),
+            _,
+        >::new()
+    };
+    {@collector_call($collector:expr, $method:ident($($arg:expr),*))} => {{
+        #[allow(unused_imports)]
+        use $crate::__private::{Collect as _, ExtendCollect as _, TryCollect as _};
+        (&$collector).$method($($arg),*)
+    }};
+    // use DoubleEndedIterator, split middle elements at the separator
+    {@emit(unpack($iter:expr), $body:block, $else:tt)
+        [$($f:tt)*] [$($o:tt)*] (split $left:tt $sep:tt $right:tt) [$($b:tt)*]
     } => {{
         let mut __iter = ::core::iter::IntoIterator::into_iter($iter);
-        $crate::iunpack!(@sized_pat(__iter, {
+        $crate::iunpack!(@sized_pat(__iter, __front, {
             $crate::iunpack!(
                 @revpat_do_iter_back(__iter, {
-                    $(
-                    let $mid = <$crate::iunpack!(
-                        @if$(($ty))?else ::std::vec::Vec<_>)
-                        as ::core::iter::FromIterator<_>>
-                        ::from_iter(__iter);
-                    )?
-                    $body
-                }, $errbody)
-                $(($bpat))*
+                    $crate::iunpack!(@opt_pat(::core::iter::Iterator::next(&mut __iter), {
+                        $crate::iunpack!(@split_star(__iter, $body, $else) $left $sep $right)
+                    }, $else, $crate::iunpack!(@count $($f)*)) $($o)*)
+                }, $else,
+                __front,
+                $crate::iunpack!(@count_req $($f)* $($b)*))
+                $($b)*
             )
-        }, { $errbody }) $($fpat),*)
+        }, $else, 0, 0, $crate::iunpack!(@count_req $($f)* $($b)*)) $($f)*)
+    }};
+    {@split_star($iter:ident, $body:block, $else:tt)
+        (* $($l:tt)*) (last $sep:tt) (* $($r:tt)*)
+    } => {{
+        $crate::iunpack!(@scan_rev($iter, __found, __mid) (* $($r)*) $sep);
+        $crate::iunpack!(@collect_into(__mid, {
+            if __found.is_none() {
+                $crate::iunpack!(@fail($crate::UnpackError::NoSeparator) $else)
+            } else {
+                $crate::iunpack!(@collect($iter, $body, $else) $($l)*)
+            }
+        }, $else) $($r)*)
+    }};
+    {@split_star($iter:ident, $body:block, $else:tt)
+        (* $($l:tt)*) $sep:tt (* $($r:tt)*)
+    } => {{
+        $crate::iunpack!(@scan($iter, __found, __mid) $sep);
+        $crate::iunpack!(@collect_into(__mid, {
+            if __found.is_none() {
+                $crate::iunpack!(@fail($crate::UnpackError::NoSeparator) $else)
+            } else {
+                $crate::iunpack!(@collect($iter, $body, $else) $($r)*)
+            }
+        }, $else) $($l)*)
     }};
+    // the elements before the separator
+    {@scan($iter:ident, $found:ident, $elems:ident) $sep:tt} => {
+        let mut $found = ::core::option::Option::None;
+        let $elems = $crate::__private::TakeWhile::new(&mut $iter, &mut $found, |__e| {
+            !$crate::iunpack!(@is_sep(__e) $sep)
+        });
+    };
+    // the elements after the last separator, buffered to keep the order
+    {@scan_rev($iter:ident, $found:ident, $elems:ident) (* $first:tt $($t:tt)*) $sep:tt} => {
+        $crate::iunpack!(@scan_rev($iter, $found, $elems) (*) $sep);
+        let $elems: $crate::__private::Vec<_> = ::core::iter::Iterator::collect($elems);
+        let $elems = ::core::iter::Iterator::rev(::core::iter::IntoIterator::into_iter($elems));
+    };
+    {@scan_rev($iter:ident, $found:ident, $elems:ident) (*) $sep:tt} => {
+        let mut $found = ::core::option::Option::None;
+        let mut __rev = ::core::iter::Iterator::rev(::core::iter::Iterator::by_ref(&mut $iter));
+        let $elems = $crate::__private::TakeWhile::new(&mut __rev, &mut $found, |__e| {
+            !$crate::iunpack!(@is_sep(__e) $sep)
+        });
+    };
+    {@is_sep($e:ident) ($sep:literal)} => {
+        ::core::matches!(*$e, $sep)
+    };
+    {@is_sep($e:ident) (== [$x:expr])} => {
+        *$e == $x
+    };
     // use DoubleEndedIterator and result mid iterator
-    {
-        $($fpat:pat ,)* *=$mid:ident $(, $bpat:pat)* $(,)?
-        = $iter:expr => $body:block
-        else $errbody:expr
+    {@emit(unpack($iter:expr), $body:block, $else:tt)
+        [$($f:tt)*] [$($o:tt)*] (*= $mid:ident) [$($b:tt)*]
     } => {{
         let mut $mid = ::core::iter::IntoIterator::into_iter($iter);
-        $crate::iunpack!(@sized_pat($mid, {
+        $crate::iunpack!(@sized_pat($mid, __front, {
             $crate::iunpack!(
                 @revpat_do_iter_back($mid, {
-                    $body
-                }, $errbody)
-                $(($bpat))*
+                    $crate::iunpack!(@opt_pat(::core::iter::Iterator::next(&mut $mid), {
+                        $body
+                    }, $else, $crate::iunpack!(@count $($f)*)) $($o)*)
+                }, $else,
+                __front,
+                $crate::iunpack!(@count_req $($f)* $($b)*))
+                $($b)*
             )
-        }, { $errbody }) $($fpat),*)
-    }};
-    // use Iterator unnamed
-    {
-        $($fpat:pat ,)* ** $(, $bpat:pat)+ $(,)?
-        = $iter:expr => $body:block
-        else $errbody:expr
-    } => {loop {
-        let mut __iter = ::core::iter::IntoIterator::into_iter($iter);
-        break $crate::iunpack!(@sized_pat(__iter, {
-            let mut __buf = [$(
-                match ::core::iter::Iterator::next(&mut __iter) {
-                    ::core::option::Option::Some(
-                        $crate::iunpack!(@if(x) else $bpat)
-                    ) => x,
-                    ::core::option::Option::None => break $errbody,
-                }
-            ),+];
-            let mut __i = 0;
-            while let ::core::option::Option::Some(__elem)
-            = ::core::iter::Iterator::next(&mut __iter) {
-                __buf[__i] = __elem;
-                __i += 1;
-                __i %= __buf.len();
-            }
-            __buf.rotate_left(__i);
-            #[allow(irrefutable_let_patterns)]
-            if let [$($bpat),+] = __buf {
-                $body
-            } else { $errbody }
-        }, { $errbody }) $($fpat),*)
+        }, $else, 0, 0, $crate::iunpack!(@count_req $($f)* $($b)*)) $($f)*)
     }};
+    // use Iterator, without end patterns
+    {@emit $data:tt [$($f:tt)*] [$($o:tt)*] (** $($mid:tt)*) []} => {
+        $crate::iunpack!(@emit $data [$($f)*] [$($o)*] (* $($mid)*) [])
+    };
     // use Iterator
-    {
-        $($fpat:pat ,)* ** $mid:ident $(: $ty:ty)? $(, $bpat:pat)+ $(,)?
-        = $iter:expr => $body:block
-        else $errbody:expr
-    } => {loop {
-        let mut __iter = ::core::iter::IntoIterator::into_iter($iter);
-        break $crate::iunpack!(@sized_pat(__iter, {
-            let mut __buf = [$(
-                match ::core::iter::Iterator::next(&mut __iter) {
-                    ::core::option::Option::Some(
-                        $crate::iunpack!(@if(x) else $bpat)
-                    ) => x,
-                    ::core::option::Option::None => break $errbody,
-                }
-            ),+];
-            let mut __i = 0;
-            let mut $mid = <$crate::iunpack!(@if$(($ty))?
-                else ::std::vec::Vec<_>)
-                as ::core::default::Default>::default();
-            while let ::core::option::Option::Some(__elem)
-            = ::core::iter::Iterator::next(&mut __iter) {
-                ::core::iter::Extend::extend(
-                    &mut $mid,
-                    ::core::option::Option::Some(
-                        ::core::mem::replace(&mut __buf[__i], __elem)
-                    )
-                );
-                __i += 1;
-                __i %= __buf.len();
-            }
-            __buf.rotate_left(__i);
-            #[allow(irrefutable_let_patterns)]
-            if let [$($bpat),+] = __buf {
+    {@emit(unpack($iter:expr), $body:block, $else:tt)
+        [$($f:tt)*] [$($o:tt)*] (** $($star:tt)*) [$($b:tt)+]
+    } => {
+        $crate::iunpack!(@emit(unpack($iter), $body, $else, forward)
+            [$($f)*] [$($o)*] (* $($star)*) [$($b)+])
+    };
+    // the middle elements of `Ends`, the bare `*` does not take them from the front
+    {@ends_mid($ends:ident, $bounds:ident) []} => {
+        ::core::result::Result::<(), $crate::UnpackError>::Ok(())
+    };
+    {@ends_mid($ends:ident, $bounds:ident) [$($star:tt)*] $($bound:tt)?} => {
+        $crate::iunpack!(@ends_collect($ends, $bounds) $($star)*)
+    };
+    {@ends_collect($ends:ident, $bounds:ident) $($star:tt)*} => {{
+        let mut __middle = $crate::__private::Ends::middle(&mut $ends);
+        match $crate::iunpack!(@collect_result(&mut __middle) $($star)*) {
+            ::core::result::Result::Ok(__star) => {
+                $bounds.check(__middle.taken()).map(|()| __star)
+            },
+            ::core::result::Result::Err(__err) => ::core::result::Result::Err(__err),
+        }
+    }};
+    (@$($t:tt)*) => {
+        ::core::compile_error!("invalid pattern of iunpack!")
+    };
+    (try $($t:tt)+) => {
+        $crate::iunpack!(@parse(try) [] $($t)+)
+    };
+    ($($t:tt)+) => {
+        $crate::iunpack!(@parse(top) [] $($t)+)
+    };
+}
+
+/// Test whether the iterator matches the patterns, like [`matches!`]
+///
+/// The patterns are the same as [`iunpack!`], the bindings can be used in guards.
+/// It only allocates for the named star patterns.
+///
+/// # Examples
+/// ```
+/// # use itermacros::imatches;
+/// assert!(imatches!("GET / HTTP/1.1".split(' '), "GET", _, "HTTP/1.1"));
+/// assert!(!imatches!("GET / HTTP/1.0".split(' '), "GET", _, "HTTP/1.1"));
+///
+/// let lines = ["add 1 2", "neg 3", "add 4"];
+/// let adds: Vec<_> = lines.iter()
+///     .filter(|line| imatches!(line.split(' '), "add", _, _))
+///     .collect();
+/// assert_eq!(adds, [&"add 1 2"]);
+///
+/// assert!(imatches!(0..8, a, b if b > a, **, 7));
+/// assert!(!imatches!(0..8, a, *{..6}));
+/// ```
+#[macro_export]
+macro_rules! imatches {
+    ($iter:expr, $($pat:tt)+) => {{
+        #[allow(unused_variables)]
+        let __matched = $crate::iunpack!($($pat)+ = $iter => {
+            true
+        } else false);
+        __matched
+    }};
+}
+
+/// Use pattern unpack iterator into `let` statement, like `let else`
+///
+/// The patterns are the same as [`iunpack!`],
+/// the bindings in the tuple of its expression form are introduced into the enclosing scope,
+/// other patterns are only checked.
+///
+/// The else block must diverge, use `else(err)` to get the [`UnpackError`],
+/// or use `else(err, consumed, rest)` like [`iunpack!`].
+///
+/// The expression after the equal sign cannot contain `else` at the top level,
+/// like `let else`, and it can be followed by a guard `if guard`.
+///
+/// # Examples
+/// ```
+/// # use itermacros::{ilet, UnpackError};
+/// # #[cfg(feature = "alloc")] {
+/// fn parse(s: &str) -> Result<(i32, Vec<&str>, i32), UnpackError> {
+///     ilet!(a, *mid, b = s.split(' ') else(err) { return Err(err) });
+///     let Ok(a) = a.parse() else { return Ok((0, mid, 0)) };
+///     let Ok(b) = b.parse() else { return Ok((a, mid, 0)) };
+///     Ok((a, mid, b))
+/// }
+/// assert_eq!(parse("1 x y 2"), Ok((1, vec!["x", "y"], 2)));
+/// assert_eq!(parse("1 2"), Ok((1, vec![], 2)));
+/// assert_eq!(parse("1"), Err(UnpackError::TooFew { got: 1, expected: 2 }));
+///
+/// ilet!(a, b, c = 0..3 else { panic!() });
+/// assert_eq!((a, b, c), (0, 1, 2));
+///
+/// ilet!(a, b if a < b, *rest = 0..5 if rest.len() == 3 else { panic!() });
+/// assert_eq!((a, b, rest), (0, 1, vec![2, 3, 4]));
+///
+/// ilet!(a, **b, 5..=9 = 0..8 else { panic!() });
+/// assert_eq!((a, b), (0, vec![1, 2, 3, 4, 5, 6]));
+///
+/// ilet!([a, b], [c, *d] = [[0, 1], [2, 3]] else { panic!() });
+/// assert_eq!((a, b, c, d), (0, 1, 2, vec![3]));
+///
+/// ilet!(name, value = "" = "key".split('=') else { panic!() });
+/// assert_eq!((name, value), ("key", ""));
+///
+/// ilet!(a, *=rest, b = 0..8 else { panic!() });
+/// assert_eq!((a, rest.collect::<Vec<_>>(), b), (0, vec![1, 2, 3, 4, 5, 6], 7));
+///
+/// ilet!(0, a = (1..).take(3) else(_err, consumed, rest) {
+///     assert_eq!(consumed, [1]);
+///     assert_eq!(rest.collect::<Vec<_>>(), [2, 3]);
+///     return;
+/// });
+///
+/// for i in 0..5 {
+///     ilet!(0, * = 0..i else { continue });
+///     assert_ne!(i, 0);
+/// }
+/// # }
+/// ```
+#[macro_export]
+macro_rules! ilet {
+    (@iter[$($e:tt)*][$($iter:tt)+] else $errbody:block) => {
+        #[allow(unused_mut, unused_braces)]
+        let ::core::option::Option::Some($crate::iunpack!(@tuple(mut)[] $($e)*))
+        = $crate::iunpack!(@tail(top) [$($e)*] $($iter)+ => {
+            ::core::option::Option::Some($crate::iunpack!(@tuple()[] $($e)*))
+        } else ::core::option::Option::None) else $errbody;
+    };
+    (@iter[$($e:tt)*][$($iter:tt)+] else($($err:ident),+) $errbody:block) => {
+        let mut __err = ::core::option::Option::None;
+        #[allow(unused_mut, unused_braces)]
+        let ::core::option::Option::Some($crate::iunpack!(@tuple(mut)[] $($e)*))
+        = $crate::iunpack!(@tail(top) [$($e)*] $($iter)+ => {
+            ::core::option::Option::Some($crate::iunpack!(@tuple()[] $($e)*))
+        } else($($err),+) {
+            __err = ::core::option::Option::Some(($($err),+));
+            ::core::option::Option::None
+        }) else {
+            let ::core::option::Option::Some(($($err),+)) = __err else {
+                ::core::unreachable!()
+            };
+            $errbody
+        };
+    };
+    (@iter[$($e:tt)*][$($iter:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::iunpack!(@scan(ilet @iter[$($e)*]) [$($iter)* $t] $($rest)*);
+    };
+    (@$($t:tt)*) => {
+        ::core::compile_error!("invalid pattern of ilet!");
+    };
+    ($($t:tt)+) => {
+        $crate::iunpack!(@parse(let) [] $($t)+);
+    };
+}
+
+/// Assert the iterator matches the patterns, and bring the bindings into scope
+///
+/// The patterns are the same as [`ilet!`], but without else,
+/// the elements must implement [`Debug`].
+///
+/// On failure, it panics with the patterns, the [`UnpackError`],
+/// the [`Debug`] of the offending element and the elements consumed so far,
+/// like [`assert_eq!`].
+///
+/// [`Debug`]: core::fmt::Debug
+///
+/// # Examples
+/// ```
+/// # use itermacros::iassert_unpack;
+/// iassert_unpack!(a, b, *rest = 0..5);
+/// assert_eq!((a, b, rest), (0, 1, vec![2, 3, 4]));
+///
+/// iassert_unpack!(a, b if a < b = [1, 2] if a + b == 3);
+/// ```
+///
+/// ```should_panic
+/// # use itermacros::iassert_unpack;
+/// // assertion `iunpack!(a, 2, * = 0..5)` failed: element 1 does not match the pattern
+/// //  element: 1
+/// // consumed: [0, 1]
+/// iassert_unpack!(a, 2, * = 0..5);
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! iassert_unpack {
+    (@iter $src:tt [$($e:tt)*] [$($iter:tt)+] $(if $($g:tt)+)?) => {
+        let mut __seen = $crate::__private::Seen::new();
+        $crate::ilet!(@iter[$($e)*][
+            $crate::__private::Seen::record(&mut __seen, $($iter)+)
+        ] $(if $($g)+)? else(__err) {
+            $crate::__private::assert_failed(::core::stringify!$src, &__err, &__seen)
+        });
+    };
+    (@iter $src:tt [$($e:tt)*] [$($iter:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::iunpack!(@scan(iassert_unpack @iter $src [$($e)*]) [$($iter)* $t] $($rest)*)
+    };
+    ($($t:tt)+) => {
+        $crate::iunpack!(@parse(assert [$($t)+]) [] $($t)+);
+    };
+}
+
+/// Use regular expression like patterns match iterator
+///
+/// All elements are buffered into [`Vec`] before matching,
+/// and the patterns are matched with backtracking, the quantifiers are greedy.
+///
+/// Like [`iunpack!`], use `=> body else ...` or the expression form,
+/// the failure is [`UnpackError::NoMatch`].
+///
+/// - Use `pattern` match an element, the bindings are in scope of body
+/// - Use `==expr` compare an element with the value of `expr` by [`PartialEq`]
+/// - Use `pattern?`, `pattern*`, `pattern+`, `pattern{n}`, `pattern{n,}`, `pattern{n,m}`
+///   match the elements with the count of quantifier,
+///   the quantified identifier collects the elements into [`Vec`],
+///   use `name @ pattern` collect the elements matched `pattern`,
+///   the bindings inside quantified patterns are rejected
+/// - Use `*name` collect any elements into [`Vec`], like `name*`
+/// - Use `*` match any elements, like `_*`
+///
+/// The quantified `==expr` is ended by the quantifier before `,` or `=`.
+///
+/// The tuple of expression form is built from the identifiers,
+/// `name @ pattern` and named star patterns in order.
+///
+/// [`PartialEq`]: core::cmp::PartialEq
+/// [`Vec`]: alloc::vec::Vec
+///
+/// # Examples
+/// ```
+/// # use itermacros::{imatch_seq, UnpackError};
+/// assert_eq!(imatch_seq!(*a, 0, *b, 0, *c = [1, 0, 2, 3, 0, 0, 4]),
+///            Ok((vec![1, 0, 2, 3], vec![], vec![4])));
+///
+/// assert_eq!(imatch_seq!(cmd, flags @ "-v"*, file, ext?, =  ["run", "-v", "-v", "a", "b"]),
+///            Ok(("run", vec!["-v", "-v"], "a", vec!["b"])));
+///
+/// assert_eq!(imatch_seq!(digits @ '0'..='9'+, '.', frac @ '0'..='9'{1,2} = "12.5".chars()),
+///            Ok((vec!['1', '2'], vec!['5'])));
+/// assert_eq!(imatch_seq!(digits @ '0'..='9'+, '.', frac @ '0'..='9'{1,2} = "12.".chars()),
+///            Err(UnpackError::NoMatch));
+///
+/// let sep = ",";
+/// assert_eq!(imatch_seq!(first, *rest, ==sep+ = ["a", "b", ",", ","] => {
+///     (first, rest)
+/// } else panic!()), ("a", vec!["b", ","]));
+///
+/// assert_eq!(imatch_seq!(Some(x), Some(_){2,}, None, * = [Some(1), Some(2), Some(3), None] => {
+///     x
+/// } else(err) {
+///     panic!("{err}")
+/// }), 1);
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! imatch_seq {
+    // parse patterns
+    (@items [$($i:tt)*] = $($rest:tt)+) => {
+        $crate::imatch_seq!(@tail [$($i)*] $($rest)+)
+    };
+    (@items [$($i:tt)*] * $name:ident $($rest:tt)*) => {
+        $crate::imatch_seq!(@next [$($i)*
+            (quant [0, <usize>::MAX] (bind $name [_]))
+        ] $($rest)*)
+    };
+    (@items [$($i:tt)*] * $($rest:tt)*) => {
+        $crate::imatch_seq!(@next [$($i)* (quant [0, <usize>::MAX] (pat [_]))] $($rest)*)
+    };
+    (@items [$($i:tt)*] $($rest:tt)+) => {
+        $crate::imatch_seq!(@item [$($i)*] [] $($rest)+)
+    };
+    (@next [$($i:tt)*] , $($rest:tt)*) => {
+        $crate::imatch_seq!(@items [$($i)*] $($rest)*)
+    };
+    (@next [$($i:tt)*] = $($rest:tt)+) => {
+        $crate::imatch_seq!(@tail [$($i)*] $($rest)+)
+    };
+    (@item [$($i:tt)*] [$($p:tt)+] ? $($rest:tt)*) => {
+        $crate::imatch_seq!(@quant [$($i)*] [$($p)+] [0, 1] (?) $($rest)*)
+    };
+    (@item [$($i:tt)*] [$($p:tt)+] * $($rest:tt)*) => {
+        $crate::imatch_seq!(@quant [$($i)*] [$($p)+] [0, <usize>::MAX] (*) $($rest)*)
+    };
+    (@item [$($i:tt)*] [$($p:tt)+] + $($rest:tt)*) => {
+        $crate::imatch_seq!(@quant [$($i)*] [$($p)+] [1, <usize>::MAX] (+) $($rest)*)
+    };
+    (@item [$($i:tt)*] [$($p:tt)+] {$n:literal} $($rest:tt)*) => {
+        $crate::imatch_seq!(@quant [$($i)*] [$($p)+] [$n, $n] ({$n}) $($rest)*)
+    };
+    (@item [$($i:tt)*] [$($p:tt)+] {$n:literal,} $($rest:tt)*) => {
+        $crate::imatch_seq!(@quant [$($i)*] [$($p)+] [$n, <usize>::MAX] ({$n,}) $($rest)*)
+    };
+    (@item [$($i:tt)*] [$($p:tt)+] {$n:literal, $m:literal} $($rest:tt)*) => {
+        $crate::imatch_seq!(@quant [$($i)*] [$($p)+] [$n, $m] ({$n, $m}) $($rest)*)
+    };
+    (@item [$($i:tt)*] [$($p:tt)+] $(, $($rest:tt)*)?) => {
+        $crate::imatch_seq!(@class [$($i)*] (one) [$($p)+] $(, $($rest)*)?)
+    };
+    (@item [$($i:tt)*] [$($p:tt)+] = $($rest:tt)+) => {
+        $crate::imatch_seq!(@class [$($i)*] (one) [$($p)+] = $($rest)+)
+    };
+    (@item [$($i:tt)*] [$($p:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::imatch_seq!(@item [$($i)*] [$($p)* $t] $($rest)*)
+    };
+    (@quant [$($i:tt)*] [$($p:tt)+] $b:tt $q:tt $(, $($rest:tt)*)?) => {
+        $crate::imatch_seq!(@class [$($i)*] (quant $b) [$($p)+] $(, $($rest)*)?)
+    };
+    (@quant [$($i:tt)*] [$($p:tt)+] $b:tt $q:tt = $($rest:tt)+) => {
+        $crate::imatch_seq!(@class [$($i)*] (quant $b) [$($p)+] = $($rest)+)
+    };
+    // not a quantifier, it is a part of pattern
+    (@quant [$($i:tt)*] [$($p:tt)+] $b:tt ($($q:tt)*) $($rest:tt)*) => {
+        $crate::imatch_seq!(@item [$($i)*] [$($p)+ $($q)*] $($rest)*)
+    };
+    (@class [$($i:tt)*] ($($k:tt)*) [$name:ident] $($rest:tt)*) => {
+        $crate::imatch_seq!(@next [$($i)* ($($k)* (bind $name))] $($rest)*)
+    };
+    (@class [$($i:tt)*] ($($k:tt)*) [$name:ident @ $($p:tt)+] $($rest:tt)*) => {
+        $crate::imatch_seq!(@next [$($i)* ($($k)* (bind $name [$($p)+]))] $($rest)*)
+    };
+    (@class [$($i:tt)*] ($($k:tt)*) [== $($x:tt)+] $($rest:tt)*) => {
+        $crate::imatch_seq!(@next [$($i)* ($($k)* (eq [$($x)+]))] $($rest)*)
+    };
+    (@class [$($i:tt)*] ($($k:tt)*) [$($p:tt)+] $($rest:tt)*) => {
+        $crate::imatch_seq!(@next [$($i)* ($($k)* (pat [$($p)+]))] $($rest)*)
+    };
+    (@tail [$($i:tt)*] $iter:expr => $body:block else $($else:tt)+) => {
+        $crate::imatch_seq!(@emit($iter, $body, [$($else)+]) $($i)*)
+    };
+    (@tail [$($i:tt)*] $iter:expr) => {
+        $crate::imatch_seq!(@emit($iter, {
+            ::core::result::Result::Ok($crate::imatch_seq!(@tuple[] $($i)*))
+        }, [(__err) {
+            ::core::result::Result::Err(__err)
+        }]) $($i)*)
+    };
+    (@tuple[$($acc:tt)*] (one (bind $name:ident $($p:tt)?)) $($i:tt)*) => {
+        $crate::imatch_seq!(@tuple[$($acc)* $name,] $($i)*)
+    };
+    (@tuple[$($acc:tt)*] (quant $b:tt (bind $name:ident $($p:tt)?)) $($i:tt)*) => {
+        $crate::imatch_seq!(@tuple[$($acc)* $name,] $($i)*)
+    };
+    (@tuple[$($acc:tt)*] $skip:tt $($i:tt)*) => {
+        $crate::imatch_seq!(@tuple[$($acc)*] $($i)*)
+    };
+    (@tuple[$($acc:tt)*]) => {
+        ($($acc)*)
+    };
+
+    // generate code
+    (@emit($iter:expr, $body:block, $else:tt) $($item:tt)+) => {{
+        let __buf: $crate::__private::Vec<_> = ::core::iter::Iterator::collect(
+            ::core::iter::IntoIterator::into_iter($iter),
+        );
+        let __lens = $crate::__private::match_seq(&__buf[..], &[$(
+            $crate::imatch_seq!(@quant_of(__buf) $item)
+        ),+]);
+        match __lens {
+            ::core::option::Option::Some(__lens) => {
+                let mut __iter = ::core::iter::IntoIterator::into_iter(__buf);
+                $crate::imatch_seq!(@extract(__iter, __lens, 0) $($item)+);
                 $body
-            } else { $errbody }
-        }, { $errbody }) $($fpat),*)
+            },
+            ::core::option::Option::None => {
+                $crate::iunpack!(@fail($crate::UnpackError::NoMatch) $else)
+            },
+        }
+    }};
+    (@quant_of($buf:ident) (one $elem:tt)) => {
+        $crate::__private::Quant::new(&$buf, 1, 1, &|__e| $crate::imatch_seq!(@pred(__e) $elem))
+    };
+    (@quant_of($buf:ident) (quant [$min:expr, $max:expr] $elem:tt)) => {
+        $crate::__private::Quant::new(&$buf, $min, $max, &|__e| {
+            $crate::imatch_seq!(@no_bind(__e) $elem);
+            $crate::imatch_seq!(@pred(__e) $elem)
+        })
+    };
+    // the or-pattern rejects the bindings of quantified pattern
+    (@no_bind($e:ident) ($kind:ident $($name:ident)? [$($lit:literal)|+])) => {};
+    (@no_bind($e:ident) (eq $x:tt)) => {};
+    (@no_bind($e:ident) ($kind:ident $($name:ident)? [$($p:tt)+])) => {
+        #[allow(unreachable_patterns, unused_parens)]
+        let ($($p)+ | _) = $e;
+    };
+    (@no_bind($e:ident) $elem:tt) => {};
+    (@pred($e:ident) (eq [$x:expr])) => {
+        *$e == $x
+    };
+    (@pred($e:ident) ($kind:ident $name:ident)) => {{
+        #[allow(unused_variables, unreachable_patterns)]
+        match $e {
+            $name => true,
+            _ => false,
+        }
+    }};
+    (@pred($e:ident) ($kind:ident $($name:ident)? [$($lit:literal)|+])) => {
+        ::core::matches!(*$e, $($lit)|+)
+    };
+    (@pred($e:ident) ($kind:ident $($name:ident)? [$($p:tt)+])) => {{
+        #[allow(unused_variables, unreachable_patterns, unused_parens)]
+        match $e {
+            $($p)+ => true,
+            _ => false,
+        }
+    }};
+    (@extract($iter:ident, $lens:ident, $idx:expr) (one $elem:tt) $($item:tt)*) => {
+        let ::core::option::Option::Some(__elem) = ::core::iter::Iterator::next(&mut $iter) else {
+            ::core::unreachable!()
+        };
+        $crate::imatch_seq!(@bind(__elem) $elem);
+        $crate::imatch_seq!(@extract($iter, $lens, $idx + 1) $($item)*);
+    };
+    (@extract($iter:ident, $lens:ident, $idx:expr)
+        (quant $b:tt (bind $name:ident $($p:tt)?)) $($item:tt)*
+    ) => {
+        let $name: $crate::__private::Vec<_> = ::core::iter::Iterator::collect(
+            ::core::iter::Iterator::take(
+                ::core::iter::Iterator::by_ref(&mut $iter),
+                $lens[$idx],
+            ),
+        );
+        $crate::imatch_seq!(@extract($iter, $lens, $idx + 1) $($item)*);
+    };
+    (@extract($iter:ident, $lens:ident, $idx:expr) (quant $b:tt $elem:tt) $($item:tt)*) => {
+        ::core::iter::Iterator::for_each(
+            ::core::iter::Iterator::take(
+                ::core::iter::Iterator::by_ref(&mut $iter),
+                $lens[$idx],
+            ),
+            ::core::mem::drop,
+        );
+        $crate::imatch_seq!(@extract($iter, $lens, $idx + 1) $($item)*);
+    };
+    (@extract($iter:ident, $lens:ident, $idx:expr)) => {};
+    (@bind($value:ident) (bind $name:ident $($p:tt)?)) => {
+        #[allow(irrefutable_let_patterns)]
+        let $name = $value else { ::core::unreachable!() };
+    };
+    (@bind($value:ident) (pat [$($p:tt)+])) => {
+        #[allow(irrefutable_let_patterns)]
+        let $($p)+ = $value else { ::core::unreachable!() };
+    };
+    (@bind($value:ident) (eq $x:tt)) => {
+        ::core::mem::drop($value);
+    };
+    (@$($t:tt)*) => {
+        ::core::compile_error!("invalid pattern of imatch_seq!")
+    };
+    ($($t:tt)+) => {
+        $crate::imatch_seq!(@items [] $($t)+)
+    };
+}
+
+/// Try multiple [`iunpack!`] patterns on an iterator, like `match` on slices
+///
+/// Each arm is `[patterns] => body`, or `[patterns] if guard => body`,
+/// the patterns are the same as [`iunpack!`],
+/// and the final arm must be `_ => body`.
+///
+/// The elements are buffered only as far as the arm needs,
+/// all elements for the star patterns, or one more than the count of patterns,
+/// so the later arms see the same elements.
+///
+/// The elements are matched by reference, like `match` on slices,
+/// so the bindings are references, and use `&"literal"` match string literal.
+///
+/// Use `imatch!(try iter { ... })` take elements tentatively from the [`Rewind`] iterator `iter`,
+/// like [`MultiPeek`], it is advanced over the elements taken by the matched arm like `iunpack!(try ...)`,
+/// before the body, so it is not advanced by the `_` arm.
+/// The end patterns delay the elements like `**`, because the arms use [`Iterator`] only.
+///
+/// # Examples
+/// ```
+/// # use itermacros::{imatch, IterUnpackExt};
+/// fn parse(s: &str) -> String {
+///     imatch!(s.split(' ') {
+///         [&"GET", path] => format!("get {path}"),
+///         [&"PUT", path, *data] if !data.is_empty() => {
+///             format!("put {path} {}", data.len())
+///         }
+///         [cmd, *] => format!("unknown {cmd}"),
+///         _ => unreachable!(),
+///     })
+/// }
+/// assert_eq!(parse("GET /"), "get /");
+/// assert_eq!(parse("GET / x"), "unknown GET");
+/// assert_eq!(parse("PUT / a b"), "put / 2");
+/// assert_eq!(parse("PUT /"), "unknown PUT");
+///
+/// assert_eq!(imatch!(0..5 {
+///     [] => 0,
+///     [a, b] => a + b,
+///     [a, b, c, **] => a + b + c,
+///     _ => unreachable!(),
+/// }), 3);
+///
+/// let mut tokens = "let x = 1".split(' ').multipeek();
+/// assert_eq!(imatch!(try tokens {
+///     [&"fn", name, *] => name.len(),
+///     _ => 0,
+/// }), 0);
+/// assert_eq!(tokens.peek(), Some(&"let"));
+///
+/// // the bare `*` takes nothing, like `iunpack!(try "let", * = tokens)`
+/// assert_eq!(imatch!(try tokens {
+///     [&"let", *] => 1,
+///     _ => 0,
+/// }), 1);
+/// assert_eq!(tokens.peek(), Some(&"x"));
+///
+/// // each arm is matched once, so many arms are fine
+/// fn eval(s: &str) -> i32 {
+///     let num = |s: &&str| s.parse::<i32>().unwrap();
+///     imatch!(s.split(' ') {
+///         [&"neg", a] => -num(a),
+///         [&"abs", a] => num(a).abs(),
+///         [&"add", a, b] => num(a) + num(b),
+///         [&"sub", a, b] => num(a) - num(b),
+///         [&"mul", a, b] => num(a) * num(b),
+///         [&"div", a, b] if num(b) != 0 => num(a) / num(b),
+///         [&"max", a, *rest] => rest.into_iter().map(num).fold(num(a), i32::max),
+///         [&"min", a, *rest] => rest.into_iter().map(num).fold(num(a), i32::min),
+///         [&"sum", *rest] => rest.into_iter().map(num).sum(),
+///         [&"len", **] => s.split(' ').count() as i32 - 1,
+///         _ => 0,
+///     })
+/// }
+/// assert_eq!(eval("sub 5 7"), -2);
+/// assert_eq!(eval("div 5 0"), 0);
+/// assert_eq!(eval("max 3 9 4"), 9);
+/// assert_eq!(eval("sum 1 2 3"), 6);
+/// assert_eq!(eval("len a b"), 2);
+///
+/// // buffer at most four elements
+/// assert_eq!(imatch!(0.. {
+///     [a, b] => a + b,
+///     [a, b, 2] => a + b,
+///     [a if *a == 0, b] => a + b,
+///     _ => -1,
+/// }), -1);
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! imatch {
+    (@arms $cfg:tt _ => $body:expr $(,)?) => ($body);
+    (@arms $cfg:tt [$($p:tt)*] $(if $guard:expr)? => $body:block , $($rest:tt)+) => {
+        $crate::imatch!(@arm $cfg [$($p)*] $(if $guard)? => $body $($rest)+)
+    };
+    (@arms $cfg:tt [$($p:tt)*] $(if $guard:expr)? => $body:block $($rest:tt)+) => {
+        $crate::imatch!(@arm $cfg [$($p)*] $(if $guard)? => $body $($rest)+)
+    };
+    (@arms $cfg:tt [$($p:tt)*] $(if $guard:expr)? => $body:expr , $($rest:tt)+) => {
+        $crate::imatch!(@arm $cfg [$($p)*] $(if $guard)? => { $body } $($rest)+)
+    };
+    (@arms $cfg:tt $($t:tt)*) => {
+        ::core::compile_error!("expected `[patterns] => body` or final `_ => body` arm of imatch!")
+    };
+    // match the arm into the tuple of its bindings, the later arms are expanded once
+    (@arm($buf:ident, $iter:ident $(, $try:tt)?) [$($p:tt)*] $(if $guard:expr)?
+        => $body:block $($rest:tt)+
+    ) => {{
+        let __arm = $crate::iunpack!(
+            @parse(sub (imatch @eval($buf, $iter $(, $try)?) [$(if $guard)?]) []) [] $($p)*
+        );
+        #[allow(unused_mut)]
+        if let ::core::option::Option::Some(
+            $crate::iunpack!(@parse(sub (imatch @pat) []) [] $($p)*)
+        ) = __arm {
+            $(
+                $crate::iunpack!(@if($crate::Tentative::commit_first(
+                    $iter,
+                    $crate::__private::Lookahead::taken(&$buf),
+                );) else $try);
+            )?
+            $body
+        } else {
+            ::core::mem::drop(__arm);
+            $crate::imatch!(@arms($buf, $iter $(, $try)?) $($rest)+)
+        }
+    }};
+    (@eval($buf:ident, $iter:ident $(, $try:tt)?) [$($guard:tt)*] [$($e:tt)*]) => {
+        $crate::iunpack!(@tail(top) [$($e)*] {
+            $crate::__private::Lookahead::fill(
+                &mut $buf,
+                &mut $iter,
+                $crate::imatch!(@need $($e)*),
+            );
+            $crate::iunpack!(@if$(($crate::__private::Lookahead::track(&$buf)) else $try)?
+                else $crate::__private::Lookahead::iter(&$buf))
+        } $($guard)* => {
+            ::core::option::Option::Some($crate::iunpack!(@tuple()[] $($e)*))
+        } else ::core::option::Option::None)
+    };
+    (@pat [$($e:tt)*]) => {
+        $crate::iunpack!(@tuple(mut)[] $($e)*)
+    };
+    // all elements for the star patterns, otherwise one more than the patterns
+    (@need $($e:tt)*) => {
+        0usize $(.saturating_add($crate::imatch!(@need_of $e)))*.saturating_add(1)
+    };
+    (@need_of (* $($t:tt)*)) => (<usize>::MAX);
+    (@need_of (*= $($t:tt)*)) => (<usize>::MAX);
+    (@need_of (while $($t:tt)*)) => (<usize>::MAX);
+    (@need_of $e:tt) => (1);
+
+    (@iter $try:tt [$($iter:tt)+] { $($arms:tt)+ }) => {{
+        let mut __buf = $crate::__private::Lookahead::default();
+        $crate::imatch!(@source $try [$($iter)+] __buf, __iter $($arms)+)
+    }};
+    (@iter $try:tt [$($iter:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::imatch!(@iter $try [$($iter)* $t] $($rest)*)
+    };
+    (@source () [$($iter:tt)+] $buf:ident, $i:ident $($arms:tt)+) => {{
+        let mut $i = ::core::iter::IntoIterator::into_iter($($iter)+);
+        $crate::imatch!(@arms($buf, $i) $($arms)+)
+    }};
+    (@source (try) [$($iter:tt)+] $buf:ident, $i:ident $($arms:tt)+) => {{
+        use $crate::Rewind as _;
+        let mut $i = ($($iter)+).tentative();
+        $crate::imatch!(@arms($buf, $i, try) $($arms)+)
     }};
+    (try $($t:tt)+) => {
+        $crate::imatch!(@iter (try) [] $($t)+)
+    };
+    ($($t:tt)+) => {
+        $crate::imatch!(@iter () [] $($t)+)
+    };
 }